@@ -0,0 +1,123 @@
+//! Compares closest hit queries through the flattened [`BvhNode`] against the pointer based
+//! tree it replaced, which visits both children of every node at the full ray length.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use raytracing_in_a_weekend::{
+    materials::Diffuse, shapes::Sphere, BvhNode, Hitable, Ray, RayHit, Vec3, AABB,
+};
+
+const RAY_COUNT: usize = 4096;
+
+struct PointerBvh {
+    left: Arc<dyn Hitable>,
+    right: Arc<dyn Hitable>,
+    bounding_box: AABB,
+}
+
+impl PointerBvh {
+    fn new(items: &[Arc<dyn Hitable>], rng: &mut SmallRng) -> Self {
+        let axis = rng.gen_range(0..3);
+        let mut items = Vec::from(items);
+        items.sort_by(|a, b| {
+            let a = a.bounding_box(0.0, 0.0).unwrap().min[axis];
+            let b = b.bounding_box(0.0, 0.0).unwrap().min[axis];
+            a.total_cmp(&b)
+        });
+        let (left, right): (Arc<dyn Hitable>, Arc<dyn Hitable>) = match items.len() {
+            1 => (items[0].clone(), items[0].clone()),
+            2 => (items[0].clone(), items[1].clone()),
+            len => (
+                Arc::new(PointerBvh::new(&items[..len / 2], rng)),
+                Arc::new(PointerBvh::new(&items[len / 2..], rng)),
+            ),
+        };
+        let bounding_box = AABB::surrounding_box(
+            &left.bounding_box(0.0, 0.0).unwrap(),
+            &right.bounding_box(0.0, 0.0).unwrap(),
+        );
+        Self {
+            left,
+            right,
+            bounding_box,
+        }
+    }
+}
+
+impl Hitable for PointerBvh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        if !self.bounding_box.hit(ray, t_min, t_max) {
+            return None;
+        }
+        match (
+            self.left.hit(ray, t_min, t_max),
+            self.right.hit(ray, t_min, t_max),
+        ) {
+            (Some(left), Some(right)) if right.distance < left.distance => Some(right),
+            (Some(left), _) => Some(left),
+            (None, right) => right,
+        }
+    }
+
+    fn bounding_box(&self, _: f32, _: f32) -> Option<AABB> {
+        Some(self.bounding_box)
+    }
+}
+
+fn random_point(rng: &mut SmallRng, extent: f32) -> Vec3 {
+    Vec3::new(
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+        rng.gen_range(-extent..extent),
+    )
+}
+
+fn spheres(count: usize, rng: &mut SmallRng) -> Vec<Arc<dyn Hitable>> {
+    let material = Diffuse::arc(Vec3::new(0.5, 0.5, 0.5));
+    (0..count)
+        .map(|_| Sphere::arc(random_point(rng, 10.0), 0.1, material.clone()))
+        .collect()
+}
+
+// Rays from outside the scene towards random points inside it
+fn rays(rng: &mut SmallRng) -> Vec<Ray> {
+    (0..RAY_COUNT)
+        .map(|_| {
+            let origin = 20.0 * random_point(rng, 1.0).unit();
+            let target = random_point(rng, 10.0);
+            Ray::new(origin, (target - origin).unit(), 0.0)
+        })
+        .collect()
+}
+
+fn trace_all(world: &dyn Hitable, rays: &[Ray]) -> usize {
+    rays.iter()
+        .filter(|ray| world.hit(ray, 0.0001, f32::MAX).is_some())
+        .count()
+}
+
+fn bench_traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("closest_hit");
+    group.throughput(Throughput::Elements(RAY_COUNT as u64));
+    for count in [1_000, 10_000, 100_000] {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let items = spheres(count, &mut rng);
+        let rays = rays(&mut rng);
+        let flat = BvhNode::new(&items, 0.0, 0.0);
+        let pointer = PointerBvh::new(&items, &mut rng);
+        assert_eq!(trace_all(&flat, &rays), trace_all(&pointer, &rays));
+
+        group.bench_with_input(BenchmarkId::new("flat", count), &rays, |b, rays| {
+            b.iter(|| trace_all(&flat, rays))
+        });
+        group.bench_with_input(BenchmarkId::new("pointer", count), &rays, |b, rays| {
+            b.iter(|| trace_all(&pointer, rays))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_traversal);
+criterion_main!(benches);
@@ -1,8 +1,22 @@
 use std::f32::consts;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
-use crate::structures::{Ray, Vec3};
+use crate::{
+    random,
+    structures::{Ray, Vec3},
+};
+
+/// Where light travelling from a point to the lens lands on the image.
+pub struct LensSample {
+    // Image coordinates, as passed to get_ray
+    pub x: f32,
+    pub y: f32,
+    pub lens_point: Vec3,
+    // Converts light leaving the point towards the lens into its contribution to the
+    // pixel it lands on, including the fall off with distance
+    pub importance: f32,
+}
 
 pub struct Camera {
     origin: Vec3,
@@ -17,6 +31,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         position: Vec3,
         look_at: Vec3,
@@ -50,11 +65,14 @@ impl Camera {
         }
     }
 
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+
     pub fn get_ray(&self, x: f32, y: f32) -> Ray {
-        let mut rng = thread_rng();
         let rd = self.lens_radius * Vec3::get_point_in_unit_sphere();
         let offset = self.u * rd.x() + self.v * rd.y();
-        let time = self.time0 + rng.gen::<f32>() * (self.time1 - self.time0);
+        let time = self.sample_time();
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + (x * self.horizontal) + (y * self.vertical)
@@ -63,4 +81,63 @@ impl Camera {
             time,
         )
     }
+
+    /// Picks a time while the shutter is open, as `get_ray` does for each ray.
+    pub fn sample_time(&self) -> f32 {
+        self.time0 + random::rng().gen::<f32>() * (self.time1 - self.time0)
+    }
+
+    /// Picks a point on the lens, as `get_ray` would, and finds where light from `point`
+    /// passing through it would land on the image. Returns None for points behind the
+    /// camera.
+    pub fn sample_lens(&self, point: &Vec3) -> Option<LensSample> {
+        let rd = self.lens_radius * Vec3::get_point_in_unit_sphere();
+        let lens_point = self.origin + self.u * rd.x() + self.v * rd.y();
+        let to_point = *point - lens_point;
+        let focus_point = self.focus_point(&lens_point, &to_point)?;
+        let planar = focus_point - self.lower_left_corner;
+        let to_focus = focus_point - lens_point;
+        // Light spreads over the image as camera rays spread over the scene, so this is
+        // the density of get_ray's directions, over the square of the distance
+        let cosine = to_focus.unit().dot(&self.forward());
+        Some(LensSample {
+            x: planar.dot(&self.horizontal) / self.horizontal.length_squared(),
+            y: planar.dot(&self.vertical) / self.vertical.length_squared(),
+            lens_point,
+            importance: to_focus.length_squared()
+                / (cosine * self.image_area() * to_point.length_squared()),
+        })
+    }
+
+    /// Probability density, per unit solid angle, of `get_ray` choosing the ray's
+    /// direction from its origin on the lens, for directions within the image.
+    pub fn direction_pdf(&self, ray: &Ray) -> f32 {
+        let focus_point = match self.focus_point(&ray.origin, &ray.direction) {
+            Some(focus_point) => focus_point,
+            None => return 0.0,
+        };
+        let to_focus = focus_point - ray.origin;
+        let cosine = to_focus.unit().dot(&self.forward());
+        to_focus.length_squared() / (cosine * self.image_area())
+    }
+
+    // Where the line from a point on the lens along the direction crosses the plane in
+    // focus, which holds the image
+    fn focus_point(&self, lens_point: &Vec3, direction: &Vec3) -> Option<Vec3> {
+        let forward = self.forward();
+        let along = direction.dot(&forward);
+        if along <= 0.0 {
+            return None;
+        }
+        let focus_distance = (self.lower_left_corner - self.origin).dot(&forward);
+        Some(*lens_point + *direction * (focus_distance / along))
+    }
+
+    fn forward(&self) -> Vec3 {
+        self.v.cross(&self.u)
+    }
+
+    fn image_area(&self) -> f32 {
+        self.horizontal.length() * self.vertical.length()
+    }
 }
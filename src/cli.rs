@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Renders a scene with a path tracer and writes it to a PNG file"
+)]
+pub struct Args {
+    /// JSON scene description to render. Renders the random sphere scene when omitted
+    pub scene: Option<PathBuf>,
+
+    /// Path of the PNG file to write
+    #[arg(short, long, default_value = "raytracing.png", value_parser = parse_png_path)]
+    pub output: PathBuf,
+
+    /// Image width in pixels, overriding the scene
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Image height in pixels, overriding the scene
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Samples per pixel, overriding the scene
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: Option<u32>,
+
+    /// How the colour seen along each camera ray is worked out
+    #[arg(short, long, value_enum, default_value_t = IntegratorKind::Path)]
+    pub integrator: IntegratorKind,
+
+    /// Number of bounces after which paths may be ended at random by Russian roulette
+    #[arg(long, default_value_t = 3)]
+    pub min_depth: u32,
+
+    /// Maximum number of bounces along each path
+    #[arg(short = 'd', long, default_value_t = 50, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: u32,
+
+    /// Distance within which surfaces occlude each other for ambient occlusion. Defaults to
+    /// a tenth of the scene's bounding box diagonal
+    #[arg(long, value_parser = parse_positive_distance)]
+    pub ao_distance: Option<f32>,
+
+    /// Distance at which the depth integrator fades to black. Defaults to the distance from
+    /// the camera to the far corner of the scene's bounding box
+    #[arg(long, value_parser = parse_positive_distance)]
+    pub max_distance: Option<f32>,
+
+    /// Number of photons traced from the lights and the environment by the photon
+    /// integrator
+    #[arg(long, default_value_t = 1_000_000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub photons: u32,
+
+    /// Distance within which photons are gathered for caustics. Defaults to a fiftieth of
+    /// the size of the region holding the mirrors and glass
+    #[arg(long, value_parser = parse_positive_distance)]
+    pub photon_radius: Option<f32>,
+
+    /// Paths traced from fresh random numbers by the Metropolis integrator, to estimate the
+    /// image's brightness and pick where its Markov chains start
+    #[arg(long, default_value_t = 100_000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub bootstrap_samples: u32,
+
+    /// Number of Markov chains run by the Metropolis integrator
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub chains: u32,
+
+    /// Rounds the Metropolis integrator's mutations are split into, writing the image so
+    /// far to the output after each. Only allowed with the Metropolis integrator
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub rounds: u32,
+
+    /// Intersection tests per ray shown as red by the BVH cost integrator
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_cost: u32,
+
+    /// Number of render threads. Defaults to one per logical core
+    #[arg(short = 'j', long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub threads: Option<u32>,
+
+    /// Seed for the random number generators. A random seed is chosen and printed when omitted
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Only print errors
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum IntegratorKind {
+    /// Path tracing with light sampling and Russian roulette
+    Path,
+    /// Bidirectional path tracing, joining paths from the camera and from the lights
+    #[value(alias = "bdpt")]
+    Bidirectional,
+    /// Path tracing with caustics through mirrors and glass taken from a photon map
+    Photon,
+    /// Metropolis light transport, mutating the random numbers paths are traced with to
+    /// explore around the ones carrying the most light. Samples are mutations per pixel
+    #[value(alias = "mlt")]
+    Metropolis,
+    /// Light reaching the first diffuse surface directly from emitters and the environment
+    Direct,
+    /// Ambient occlusion, with no materials or lights
+    #[value(alias = "ao")]
+    AmbientOcclusion,
+    /// Surface normals as colours
+    Normals,
+    /// Distance from the camera as a grey level
+    Depth,
+    /// Heat map of bounding box and primitive tests per camera ray
+    BvhCost,
+}
+
+fn parse_positive_distance(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(distance) if distance > 0.0 && distance.is_finite() => Ok(distance),
+        Ok(_) => Err("must be greater than zero".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn parse_png_path(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case("png") => Ok(path),
+        _ => Err("only PNG output is supported, the path must end in .png".to_string()),
+    }
+}
@@ -0,0 +1,50 @@
+use crate::structures::Vec3;
+
+// Wavelengths in nanometres over which spectra are integrated
+const FIRST_WAVELENGTH: u32 = 380;
+const LAST_WAVELENGTH: u32 = 780;
+const WAVELENGTH_STEP: u32 = 5;
+// Second radiation constant, hc/k, in metre kelvins
+const SECOND_RADIATION_CONSTANT: f64 = 1.4388e-2;
+
+/// Linear sRGB colour of a black body at a temperature in kelvin, scaled to a luminance
+/// of one so that only its hue changes with temperature. Returns black for temperatures
+/// too low to glow.
+pub fn blackbody(kelvin: f32) -> Vec3 {
+    let (mut x, mut y, mut z) = (0.0f64, 0.0f64, 0.0f64);
+    for wavelength in (FIRST_WAVELENGTH..=LAST_WAVELENGTH).step_by(WAVELENGTH_STEP as usize) {
+        let nanometres = wavelength as f64;
+        let metres = nanometres * 1e-9;
+        // Planck's law, leaving out constant factors which the normalisation removes
+        let radiance = 1.0
+            / (metres.powi(5)
+                * ((SECOND_RADIATION_CONSTANT / (metres * kelvin as f64)).exp() - 1.0));
+        let (x_bar, y_bar, z_bar) = colour_matching(nanometres);
+        x += radiance * x_bar;
+        y += radiance * y_bar;
+        z += radiance * z_bar;
+    }
+    if !(y > 0.0 && y.is_finite()) {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    let (x, y, z) = (x / y, 1.0, z / y);
+    Vec3::new(
+        (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0) as f32,
+        (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0) as f32,
+        (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0) as f32,
+    )
+}
+
+// The CIE 1931 standard observer, from the multi-lobe fit by Wyman, Sloan and Shirley
+fn colour_matching(nanometres: f64) -> (f64, f64, f64) {
+    let lobe = |mean: f64, below: f64, above: f64| {
+        let width = if nanometres < mean { below } else { above };
+        (-0.5 * ((nanometres - mean) / width).powi(2)).exp()
+    };
+    (
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
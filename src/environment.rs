@@ -0,0 +1,21 @@
+use crate::structures::Vec3;
+
+pub struct EnvironmentSample {
+    pub direction: Vec3,
+    pub pdf: f32,
+}
+
+pub trait Environment: Sync + Send {
+    fn colour(&self, direction: &Vec3) -> Vec3;
+
+    /// Chooses a direction in proportion to the light arriving from it. Environments
+    /// which return None are only found by rays scattered from surfaces.
+    fn sample(&self) -> Option<EnvironmentSample> {
+        None
+    }
+
+    /// Probability density, per unit solid angle, of `sample` choosing `direction`.
+    fn pdf(&self, _direction: &Vec3) -> f32 {
+        0.0
+    }
+}
@@ -0,0 +1,199 @@
+use std::{f32::consts, path::Path};
+
+use image::ImageError;
+use rand::Rng;
+
+use crate::{
+    environment::{Environment, EnvironmentSample},
+    random,
+    structures::Vec3,
+    textures::load_linear_image,
+};
+
+/// An equirectangular (latitude-longitude) image surrounding the scene, with +y up and
+/// the centre of the image looking down -z.
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+    intensity: f32,
+    rotation: f32,
+    distribution: Option<Distribution2D>,
+}
+
+impl EnvironmentMap {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "An environment map must have one colour per pixel"
+        );
+        // Pixels are weighted by their solid angle, which shrinks towards the poles
+        let weights: Vec<f32> = pixels
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                let theta = ((index / width) as f32 + 0.5) / height as f32 * consts::PI;
+                luminance(pixel) * theta.sin()
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            pixels,
+            intensity: 1.0,
+            rotation: 0.0,
+            distribution: Distribution2D::new(&weights, width, height),
+        }
+    }
+
+    /// Loads a Radiance HDR image, or any other image format supported by `image`,
+    /// which is assumed to be gamma 2 encoded like our own output.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let (width, height, pixels) = load_linear_image(path.as_ref())?;
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Rotates the map about the vertical axis.
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees * consts::PI / 180.0;
+        self
+    }
+
+    fn direction_to_uv(&self, direction: &Vec3) -> (f32, f32) {
+        let unit_direction = direction.unit();
+        let phi = f32::atan2(unit_direction.x(), -unit_direction.z()) + self.rotation;
+        let theta = unit_direction.y().clamp(-1.0, 1.0).acos();
+        (
+            (phi / (2.0 * consts::PI) + 0.5).rem_euclid(1.0),
+            theta / consts::PI,
+        )
+    }
+
+    fn uv_to_direction(&self, u: f32, v: f32) -> Vec3 {
+        let phi = (u - 0.5) * 2.0 * consts::PI - self.rotation;
+        let theta = v * consts::PI;
+        Vec3::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    fn uv_to_pixel(&self, u: f32, v: f32) -> (usize, usize) {
+        (
+            ((u * self.width as f32) as usize).min(self.width - 1),
+            ((v * self.height as f32) as usize).min(self.height - 1),
+        )
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn colour(&self, direction: &Vec3) -> Vec3 {
+        let (u, v) = self.direction_to_uv(direction);
+        let (x, y) = self.uv_to_pixel(u, v);
+        self.intensity * self.pixels[y * self.width + x]
+    }
+
+    fn sample(&self) -> Option<EnvironmentSample> {
+        let distribution = self.distribution.as_ref()?;
+        let mut rng = random::rng();
+        let (x, y) = distribution.sample(rng.gen(), rng.gen());
+        // Pick a point uniformly within the chosen pixel
+        let u = (x as f32 + rng.gen::<f32>()) / self.width as f32;
+        let v = (y as f32 + rng.gen::<f32>()) / self.height as f32;
+        let direction = self.uv_to_direction(u, v);
+        let pdf = self.pdf(&direction);
+        if pdf > 0.0 {
+            Some(EnvironmentSample { direction, pdf })
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, direction: &Vec3) -> f32 {
+        let distribution = match &self.distribution {
+            Some(distribution) => distribution,
+            None => return 0.0,
+        };
+        let (u, v) = self.direction_to_uv(direction);
+        let sin_theta = (v * consts::PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (x, y) = self.uv_to_pixel(u, v);
+        // Convert the density over the image to a density over the sphere of directions
+        let image_pdf = distribution.probability(x, y) * (self.width * self.height) as f32;
+        image_pdf / (2.0 * consts::PI * consts::PI * sin_theta)
+    }
+}
+
+fn luminance(colour: &Vec3) -> f32 {
+    0.2126 * colour.r() + 0.7152 * colour.g() + 0.0722 * colour.b()
+}
+
+/// Piecewise constant distribution over the pixels of an image, sampled by choosing a row
+/// from the marginal distribution and then a column from that row.
+struct Distribution2D {
+    width: usize,
+    height: usize,
+    // Running totals of the weights along each row, and of the row totals
+    row_cdfs: Vec<f32>,
+    marginal_cdf: Vec<f32>,
+    total: f32,
+}
+
+impl Distribution2D {
+    fn new(weights: &[f32], width: usize, height: usize) -> Option<Self> {
+        let mut row_cdfs = Vec::with_capacity(width * height);
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for row in weights.chunks(width) {
+            let mut row_total = 0.0;
+            for weight in row {
+                row_total += weight.max(0.0);
+                row_cdfs.push(row_total);
+            }
+            total += row_total;
+            marginal_cdf.push(total);
+        }
+        if total > 0.0 && total.is_finite() {
+            Some(Self {
+                width,
+                height,
+                row_cdfs,
+                marginal_cdf,
+                total,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn sample(&self, row_sample: f32, column_sample: f32) -> (usize, usize) {
+        let y = find_interval(&self.marginal_cdf, row_sample * self.total).min(self.height - 1);
+        let row = &self.row_cdfs[y * self.width..(y + 1) * self.width];
+        let x = find_interval(row, column_sample * row[self.width - 1]).min(self.width - 1);
+        (x, y)
+    }
+
+    fn probability(&self, x: usize, y: usize) -> f32 {
+        let index = y * self.width + x;
+        let previous = if x == 0 {
+            0.0
+        } else {
+            self.row_cdfs[index - 1]
+        };
+        (self.row_cdfs[index] - previous) / self.total
+    }
+}
+
+// Index of the first entry of the cumulative weights which exceeds the value
+fn find_interval(cdf: &[f32], value: f32) -> usize {
+    cdf.partition_point(|&cumulative| cumulative <= value)
+}
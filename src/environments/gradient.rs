@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use crate::{environment::Environment, structures::Vec3};
+
+/// Vertical blend from the colour looking straight down to the colour looking straight up.
+pub struct Gradient {
+    bottom: Vec3,
+    top: Vec3,
+}
+
+impl Gradient {
+    pub fn new(bottom: Vec3, top: Vec3) -> Self {
+        Self { bottom, top }
+    }
+
+    pub fn arc(bottom: Vec3, top: Vec3) -> Arc<dyn Environment> {
+        Arc::new(Self::new(bottom, top))
+    }
+
+    /// The white to blue sky used throughout the books.
+    pub fn sky() -> Arc<dyn Environment> {
+        Self::arc(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Environment for Gradient {
+    fn colour(&self, direction: &Vec3) -> Vec3 {
+        let unit_direction = direction.unit();
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
@@ -0,0 +1,7 @@
+mod environment_map;
+mod gradient;
+mod solid_colour;
+
+pub use environment_map::*;
+pub use gradient::*;
+pub use solid_colour::*;
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::{environment::Environment, structures::Vec3};
+
+pub struct SolidColour {
+    colour: Vec3,
+}
+
+impl SolidColour {
+    pub fn new(colour: Vec3) -> Self {
+        Self { colour }
+    }
+
+    pub fn arc(colour: Vec3) -> Arc<dyn Environment> {
+        Arc::new(Self::new(colour))
+    }
+}
+
+impl Environment for SolidColour {
+    fn colour(&self, _direction: &Vec3) -> Vec3 {
+        self.colour
+    }
+}
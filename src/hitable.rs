@@ -9,10 +9,73 @@ pub struct RayHit {
     pub distance: f32,
     pub point: Vec3,
     pub normal: Vec3,
+    pub u: f32,
+    pub v: f32,
     pub material: Arc<dyn Material>,
 }
 
+/// A point picked on the surface of a shape, for starting paths from lights.
+pub struct SurfaceSample {
+    // A hit at zero distance, giving the point, its normal and the emitter's material
+    pub hit: RayHit,
+    // Probability density, per unit area, of picking the point
+    pub pdf: f32,
+}
+
 pub trait Hitable: Sync + Send {
     fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit>;
     fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB>;
+
+    /// Fraction of light passing along the ray between `t_min` and `t_max`, used for
+    /// shadow rays. Solid shapes block everything they hit, while participating media
+    /// return an estimate of how much light they let through.
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if self.hit(ray, t_min, t_max).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Number of bounding box and primitive intersection tests made by `hit`, for
+    /// visualising where rays are expensive to trace.
+    fn hit_cost(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> usize {
+        1
+    }
+
+    /// Picks a direction from `origin` towards a point on the shape, so that emissive
+    /// shapes can be sampled as lights. Shapes which can't be sampled return None.
+    fn sample_direction(&self, _origin: &Vec3) -> Option<Vec3> {
+        None
+    }
+
+    /// Probability density, per unit solid angle, of `sample_direction` choosing the
+    /// ray's direction from its origin.
+    fn direction_pdf(&self, _ray: &Ray) -> f32 {
+        0.0
+    }
+
+    /// Picks a point uniformly over the shape's surface, so that paths can be traced out
+    /// from emissive shapes. Shapes which can't be sampled return None.
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        None
+    }
+
+    /// Probability density, per unit area, of `sample_surface` picking the point where
+    /// the ray hits the shape.
+    fn surface_pdf(&self, _ray: &Ray) -> f32 {
+        0.0
+    }
+}
+
+/// Converts the density of picking a point uniformly on a surface of the given area to a
+/// density per unit solid angle, as seen from the origin of the ray which hit it.
+pub(crate) fn solid_angle_pdf(ray: &Ray, hit: &RayHit, area: f32) -> f32 {
+    let ray_length = ray.direction.length();
+    let distance = hit.distance * ray_length;
+    let cosine = (hit.normal.dot(&ray.direction) / ray_length).abs();
+    if cosine <= 0.0 {
+        return 0.0;
+    }
+    distance.powi(2) / (cosine * area)
 }
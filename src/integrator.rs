@@ -0,0 +1,229 @@
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    renderer::{Framebuffer, RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Works out the colour seen along each camera ray. The path tracers estimate the light
+/// arriving, while the others visualise the scene to help with debugging it.
+pub trait Integrator: Sync + Send {
+    /// Light arriving back along `ray`. Light the integrator finds reaching other pixels,
+    /// such as by tracing paths out from the lights, is added to `splats`.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        splats: &Splats,
+    ) -> Vec3;
+
+    /// Renders the whole image at once, for integrators which don't sample each pixel in
+    /// turn. Returns None, the default, to have `render` call `radiance` for each pixel.
+    fn render(
+        &self,
+        _world: &dyn Hitable,
+        _camera: &Camera,
+        _settings: &RenderSettings,
+    ) -> Option<Framebuffer> {
+        None
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use super::Integrator;
+    use crate::{
+        camera::Camera,
+        environment::Environment,
+        environments::SolidColour,
+        hitable::Hitable,
+        materials::{Diffuse, DiffuseLight},
+        renderer::{render, Framebuffer, RenderSettings},
+        shapes::{Sphere, XyRect, XzRect, YzRect},
+        structures::{BvhNode, Vec3},
+    };
+
+    /// Everything but the integrator needed to render a small test image.
+    pub struct Scene {
+        pub world: BvhNode,
+        pub camera: Camera,
+        pub environment: Arc<dyn Environment>,
+        pub lights: Vec<Arc<dyn Hitable>>,
+        pub width: usize,
+        pub height: usize,
+    }
+
+    impl Scene {
+        /// Sees `shapes` through a pinhole camera at `look_from`, looking at `look_at` with
+        /// a vertical field of view of `fov` degrees. Lit by nothing until `environment`
+        /// or `lights` are set.
+        pub fn new(
+            shapes: &[Arc<dyn Hitable>],
+            look_from: Vec3,
+            look_at: Vec3,
+            fov: f32,
+            width: usize,
+            height: usize,
+        ) -> Self {
+            Self {
+                world: BvhNode::new(shapes, 0.0, 0.0),
+                camera: Camera::new(
+                    look_from,
+                    look_at,
+                    Vec3::new(0.0, 1.0, 0.0),
+                    fov,
+                    width as f32 / height as f32,
+                    0.0,
+                    1.0,
+                    0.0,
+                    0.0,
+                ),
+                environment: SolidColour::arc(Vec3::new(0.0, 0.0, 0.0)),
+                lights: Vec::new(),
+                width,
+                height,
+            }
+        }
+
+        pub fn settings(
+            &self,
+            integrator: Arc<dyn Integrator>,
+            samples: usize,
+            seed: u64,
+        ) -> RenderSettings {
+            RenderSettings {
+                width: self.width,
+                height: self.height,
+                samples,
+                seed,
+                environment: self.environment.clone(),
+                lights: self.lights.clone(),
+                integrator,
+            }
+        }
+
+        pub fn render(
+            &self,
+            integrator: Arc<dyn Integrator>,
+            samples: usize,
+            seed: u64,
+        ) -> Framebuffer {
+            render(
+                &self.world,
+                &self.camera,
+                &self.settings(integrator, samples, seed),
+            )
+        }
+    }
+
+    /// A small diffuse Cornell box, open at the front and top, and lit by a panel which is
+    /// just out of view so that no pixel is dominated by its edges.
+    pub fn cornell_box() -> Scene {
+        let white = Diffuse::arc(Vec3::new(0.73, 0.73, 0.73));
+        let light = XzRect::arc(
+            0.35,
+            0.65,
+            0.35,
+            0.65,
+            0.9,
+            DiffuseLight::arc(Vec3::new(8.0, 8.0, 8.0)),
+        );
+        let shapes = [
+            XzRect::arc(0.0, 1.0, 0.0, 1.0, 0.0, white.clone()),
+            XyRect::arc(0.0, 1.0, 0.0, 1.0, 1.0, white.clone()),
+            YzRect::arc(
+                0.0,
+                1.0,
+                0.0,
+                1.0,
+                0.0,
+                Diffuse::arc(Vec3::new(0.65, 0.05, 0.05)),
+            ),
+            YzRect::arc(
+                0.0,
+                1.0,
+                0.0,
+                1.0,
+                1.0,
+                Diffuse::arc(Vec3::new(0.12, 0.45, 0.15)),
+            ),
+            Sphere::arc(Vec3::new(0.5, 0.25, 0.55), 0.25, white),
+            light.clone(),
+        ];
+        let mut scene = Scene::new(
+            &shapes,
+            Vec3::new(0.5, 0.5, -1.2),
+            Vec3::new(0.5, 0.3, 0.5),
+            40.0,
+            8,
+            8,
+        );
+        scene.lights.push(light);
+        scene
+    }
+
+    /// Averages square blocks of `block` pixels, smoothing out the noise in an image.
+    pub fn block_means(framebuffer: &Framebuffer, block: usize) -> Vec<Vec3> {
+        let columns = framebuffer.width() / block;
+        let mut means = vec![Vec3::new(0.0, 0.0, 0.0); columns * (framebuffer.height() / block)];
+        for y in 0..framebuffer.height() {
+            for x in 0..framebuffer.width() {
+                means[(y / block) * columns + x / block] +=
+                    framebuffer.pixel(x, y) / (block * block) as f32;
+            }
+        }
+        means
+    }
+
+    /// Checks every channel of the whole of `actual` is within `mean_tolerance` of
+    /// `expected`, and of each block of `block` pixels within `block_tolerance`, as
+    /// fractions of the expected value. Blocks are also allowed a little absolute
+    /// error, so that nearly black ones don't fail on noise.
+    pub fn assert_converges(
+        expected: &Framebuffer,
+        actual: &Framebuffer,
+        mean_tolerance: f32,
+        block: usize,
+        block_tolerance: f32,
+    ) {
+        let mean = |framebuffer: &Framebuffer| {
+            let pixels = framebuffer.pixels();
+            pixels
+                .iter()
+                .fold(Vec3::new(0.0, 0.0, 0.0), |total, &pixel| total + pixel)
+                / pixels.len() as f32
+        };
+        let whole = (mean(expected), mean(actual));
+        for channel in 0..3 {
+            let (expected, actual) = (whole.0[channel], whole.1[channel]);
+            assert!(
+                (actual - expected).abs() <= mean_tolerance * expected,
+                "mean channel {} is {}, expected {}",
+                channel,
+                actual,
+                expected
+            );
+        }
+        for (index, (expected, actual)) in block_means(expected, block)
+            .into_iter()
+            .zip(block_means(actual, block))
+            .enumerate()
+        {
+            for channel in 0..3 {
+                assert!(
+                    (actual[channel] - expected[channel]).abs()
+                        <= block_tolerance * expected[channel] + 1e-3,
+                    "block {} channel {} is {}, expected {}",
+                    index,
+                    channel,
+                    actual[channel],
+                    expected[channel]
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use super::display;
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// White where the hemisphere above a surface is open, and black where other surfaces
+/// closer than `distance` block it, with nothing lit and no materials applied.
+pub struct AmbientOcclusion {
+    distance: f32,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f32) -> Self {
+        Self { distance }
+    }
+
+    pub fn arc(distance: f32) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(distance))
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        _settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let open = Vec3::new(1.0, 1.0, 1.0);
+        let hit = match world.hit(ray, 0.0001, f32::MAX) {
+            Some(hit) => hit,
+            None => return open,
+        };
+        // Two sided surfaces are occluded on whichever side the camera sees
+        let normal = if hit.normal.dot(&ray.direction) > 0.0 {
+            -hit.normal
+        } else {
+            hit.normal
+        };
+        let mut direction = normal + Vec3::get_point_on_unit_sphere();
+        if direction.length_squared() < 1e-8 {
+            direction = normal;
+        }
+        let occlusion_ray = Ray::new(hit.point, direction.unit(), ray.time);
+        match world.hit(&occlusion_ray, 0.0001, self.distance) {
+            Some(_) => Vec3::new(0.0, 0.0, 0.0),
+            None => display(open),
+        }
+    }
+}
@@ -0,0 +1,694 @@
+use std::sync::Arc;
+
+use super::{
+    lighting::{
+        emission_pdf, environment_light, sample_emission, sample_environment, sample_light_surface,
+    },
+    survives_roulette,
+};
+use crate::{
+    camera::Camera,
+    hitable::{Hitable, RayHit},
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Bidirectional path tracing. Each sample traces a subpath from the camera and another
+/// from a point on one of the lights, then joins every vertex of one to every vertex of
+/// the other, weighting each way of making the same path by multiple importance sampling.
+/// Joining light subpaths straight to the camera splats light onto whichever pixel it
+/// lands on, which finds caustics seen on diffuse surfaces.
+///
+/// The environment is lit as by `PathTracer`, as light subpaths only start from `lights`.
+pub struct BidirectionalPathTracer {
+    // Bounces after which subpaths may be ended by Russian roulette
+    min_depth: u32,
+    // Bounces after which paths are always ended, counting both subpaths
+    max_depth: u32,
+}
+
+impl BidirectionalPathTracer {
+    pub fn new(min_depth: u32, max_depth: u32) -> Self {
+        Self {
+            min_depth,
+            max_depth,
+        }
+    }
+
+    pub fn arc(min_depth: u32, max_depth: u32) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(min_depth, max_depth))
+    }
+}
+
+enum VertexKind {
+    Camera,
+    // A point on an emitter, where a light subpath starts
+    Light(RayHit),
+    // A point on a surface or in a medium, found by the ray arriving at it
+    Scatter { hit: RayHit, in_ray: Ray },
+}
+
+struct Vertex {
+    kind: VertexKind,
+    point: Vec3,
+    // Light, or the camera's sensitivity to it, carried along the subpath to the vertex
+    // over the density with which the subpath was sampled
+    throughput: Vec3,
+    // Set where the material scattered specularly, so no connection can be made
+    specular: bool,
+    // Densities of choosing the vertex from the subpath which found it, and from the
+    // vertex after it running the other way. Light vertices use densities per unit area,
+    // while the rest leave out the cosine at the vertex, which cancels wherever the two
+    // are compared and is meaningless inside media
+    pdf_forward: f32,
+    pdf_reverse: f32,
+}
+
+impl Vertex {
+    fn hit(&self) -> Option<&RayHit> {
+        match &self.kind {
+            VertexKind::Camera => None,
+            VertexKind::Light(hit) | VertexKind::Scatter { hit, .. } => Some(hit),
+        }
+    }
+
+    // Density with which this vertex picks `next`, given light arriving from `previous`,
+    // or along the ray which found the vertex when there is no previous vertex.
+    fn pdf(&self, previous: Option<&Vertex>, next: &Vertex, camera: &Camera, time: f32) -> f32 {
+        let direction = next.point - self.point;
+        let pdf = match &self.kind {
+            VertexKind::Camera => camera.direction_pdf(&Ray::new(self.point, direction, time)),
+            VertexKind::Light(hit) => emission_pdf(hit, &direction),
+            VertexKind::Scatter { hit, in_ray } => {
+                let in_ray = match previous {
+                    Some(previous) => Ray::new(previous.point, self.point - previous.point, time),
+                    None => *in_ray,
+                };
+                hit.material.pdf(&in_ray, hit, &direction.unit())
+            }
+        };
+        next.convert_pdf(pdf, &self.point)
+    }
+
+    // Converts a density per unit solid angle, as seen from `origin`, to this vertex's
+    // measure
+    fn convert_pdf(&self, pdf: f32, origin: &Vec3) -> f32 {
+        let offset = self.point - *origin;
+        let distance_squared = offset.length_squared();
+        if distance_squared <= 0.0 {
+            return 0.0;
+        }
+        match &self.kind {
+            VertexKind::Light(hit) => {
+                pdf * hit.normal.dot(&offset).abs() / (distance_squared * distance_squared.sqrt())
+            }
+            _ => pdf / distance_squared,
+        }
+    }
+
+    // The material's response to light from the previous vertex leaving towards `point`,
+    // including the cosine there, or the cosine alone for light leaving an emitter
+    fn scattering(&self, point: &Vec3) -> Vec3 {
+        let direction = (*point - self.point).unit();
+        match &self.kind {
+            VertexKind::Camera => Vec3::new(0.0, 0.0, 0.0),
+            VertexKind::Light(hit) => {
+                let cosine = hit.normal.dot(&direction).abs();
+                Vec3::new(cosine, cosine, cosine)
+            }
+            VertexKind::Scatter { hit, in_ray } => hit.material.eval(in_ray, hit, &direction),
+        }
+    }
+}
+
+impl Integrator for BidirectionalPathTracer {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        splats: &Splats,
+    ) -> Vec3 {
+        let max_depth = self.max_depth as usize;
+        let mut camera_path = vec![Vertex {
+            kind: VertexKind::Camera,
+            point: ray.origin,
+            throughput: Vec3::new(1.0, 1.0, 1.0),
+            specular: false,
+            pdf_forward: 1.0,
+            pdf_reverse: 0.0,
+        }];
+        let mut colour = self.random_walk(
+            world,
+            settings,
+            *ray,
+            Vec3::new(1.0, 1.0, 1.0),
+            camera.direction_pdf(ray),
+            max_depth + 2,
+            &mut camera_path,
+            true,
+        );
+        let light_path = self.light_path(world, settings, ray.time);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                // Joining a light straight to the camera only finds lights seen directly,
+                // which the camera subpath already finds
+                if s + t < 2 || s + t - 2 > max_depth || (s == 1 && t == 1) {
+                    continue;
+                }
+                colour += self.connect(
+                    world,
+                    camera,
+                    settings,
+                    splats,
+                    &camera_path,
+                    &light_path,
+                    s,
+                    t,
+                    ray.time,
+                );
+            }
+        }
+        colour
+    }
+}
+
+impl BidirectionalPathTracer {
+    // Starts a subpath at a point on one of the lights, picked at random
+    fn light_path(&self, world: &dyn Hitable, settings: &RenderSettings, time: f32) -> Vec<Vertex> {
+        let mut path = Vec::new();
+        let light_vertex = match sample_light(settings) {
+            Some(light_vertex) => light_vertex,
+            None => return path,
+        };
+        let hit = match &light_vertex.kind {
+            VertexKind::Light(hit) => hit,
+            _ => unreachable!("sample_light always returns a light vertex"),
+        };
+        let direction = sample_emission(hit);
+        let pdf = emission_pdf(hit, &direction);
+        if pdf <= 0.0 {
+            return path;
+        }
+        let ray = Ray::new(light_vertex.point, direction, time);
+        let throughput =
+            light_vertex.throughput * light_vertex.scattering(&ray.point_at(1.0)) / pdf;
+        path.push(light_vertex);
+        self.random_walk(
+            world,
+            settings,
+            ray,
+            throughput,
+            pdf,
+            self.max_depth as usize + 1,
+            &mut path,
+            false,
+        );
+        path
+    }
+
+    // Extends the subpath from its last vertex along `ray`, which was chosen with the
+    // given density per unit solid angle, until it leaves the scene, is absorbed or has
+    // `max_vertices`. Camera subpaths also gather the light from the environment.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        world: &dyn Hitable,
+        settings: &RenderSettings,
+        mut ray: Ray,
+        mut throughput: Vec3,
+        mut pdf: f32,
+        max_vertices: usize,
+        path: &mut Vec<Vertex>,
+        gather_environment: bool,
+    ) -> Vec3 {
+        let mut environment = Vec3::new(0.0, 0.0, 0.0);
+        // As pdf, but zero where the ray could not also have been found by sampling the
+        // environment
+        let mut scatter_pdf = 0.0;
+        let mut bounces = 0;
+        while path.len() < max_vertices {
+            let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+                Some(hit) => hit,
+                None => {
+                    if gather_environment {
+                        environment += throughput * environment_light(&ray, scatter_pdf, settings);
+                    }
+                    break;
+                }
+            };
+            let previous = path.len() - 1;
+            let previous_point = path[previous].point;
+            let distance_squared = (hit.point - previous_point).length_squared();
+            let pdf_forward = if distance_squared > 0.0 {
+                pdf / distance_squared
+            } else {
+                0.0
+            };
+
+            let sample = if path.len() + 1 < max_vertices {
+                if gather_environment {
+                    environment += throughput * sample_environment(&ray, &hit, world, settings);
+                }
+                hit.material.sample(&ray, &hit)
+            } else {
+                None
+            };
+            if let Some(sample) = &sample {
+                if !sample.specular {
+                    let reverse_ray = Ray::new(hit.point, -sample.direction, ray.time);
+                    let to_previous = (previous_point - hit.point).unit();
+                    let reverse_pdf = hit.material.pdf(&reverse_ray, &hit, &to_previous);
+                    path[previous].pdf_reverse =
+                        path[previous].convert_pdf(reverse_pdf, &hit.point);
+                }
+            }
+            let point = hit.point;
+            path.push(Vertex {
+                kind: VertexKind::Scatter { hit, in_ray: ray },
+                point,
+                throughput,
+                specular: sample.as_ref().is_some_and(|sample| sample.specular),
+                pdf_forward,
+                pdf_reverse: 0.0,
+            });
+
+            let sample = match sample {
+                Some(sample) => sample,
+                None => break,
+            };
+            throughput *= sample.weight;
+            scatter_pdf = if sample.specular { 0.0 } else { sample.pdf };
+            pdf = scatter_pdf;
+            ray = Ray::new(point, sample.direction, ray.time);
+            bounces += 1;
+            if bounces >= self.min_depth && !survives_roulette(&mut throughput) {
+                break;
+            }
+        }
+        environment
+    }
+
+    // Light carried by the path made of the first `s` vertices of the light subpath and
+    // the first `t` of the camera subpath, weighted against the other ways of making it.
+    // Light reaching the camera through a new point on the lens is splatted, and nothing
+    // is returned for it.
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        &self,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        splats: &Splats,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        s: usize,
+        t: usize,
+        time: f32,
+    ) -> Vec3 {
+        let no_light = Vec3::new(0.0, 0.0, 0.0);
+        if s == 0 {
+            // The camera subpath found an emitter by itself
+            let camera_end = &camera_path[t - 1];
+            let emitted = match camera_end.hit() {
+                Some(hit) => hit.material.emitted(hit),
+                None => return no_light,
+            };
+            if emitted.length_squared() <= 0.0 {
+                return no_light;
+            }
+            let weight = self.mis_weight(
+                camera,
+                settings,
+                camera_path,
+                light_path,
+                camera_end,
+                None,
+                s,
+                t,
+                time,
+            );
+            return weight * camera_end.throughput * emitted;
+        }
+
+        if t == 1 {
+            let light_end = &light_path[s - 1];
+            if light_end.specular {
+                return no_light;
+            }
+            let lens = match camera.sample_lens(&light_end.point) {
+                Some(lens) => lens,
+                None => return no_light,
+            };
+            let contribution =
+                light_end.throughput * light_end.scattering(&lens.lens_point) * lens.importance;
+            if contribution.length_squared() <= 0.0 {
+                return no_light;
+            }
+            let transmittance = transmittance(world, &light_end.point, &lens.lens_point, time);
+            if transmittance <= 0.0 {
+                return no_light;
+            }
+            let camera_vertex = Vertex {
+                kind: VertexKind::Camera,
+                point: lens.lens_point,
+                throughput: Vec3::new(1.0, 1.0, 1.0),
+                specular: false,
+                pdf_forward: 1.0,
+                pdf_reverse: 0.0,
+            };
+            let weight = self.mis_weight(
+                camera,
+                settings,
+                camera_path,
+                light_path,
+                &camera_vertex,
+                Some(light_end),
+                s,
+                t,
+                time,
+            );
+            splats.add(lens.x, lens.y, weight * transmittance * contribution);
+            return no_light;
+        }
+
+        let camera_end = &camera_path[t - 1];
+        if camera_end.specular {
+            return no_light;
+        }
+        // Rather than the start of the light subpath, a new point on a light is used, as
+        // it is independent of the camera subpath it is joined to
+        let sampled_light;
+        let light_end = if s == 1 {
+            sampled_light = match sample_light(settings) {
+                Some(light_vertex) => light_vertex,
+                None => return no_light,
+            };
+            &sampled_light
+        } else {
+            &light_path[s - 1]
+        };
+        if light_end.specular {
+            return no_light;
+        }
+        let distance_squared = (light_end.point - camera_end.point).length_squared();
+        if distance_squared <= 0.0 {
+            return no_light;
+        }
+        let contribution = camera_end.throughput
+            * camera_end.scattering(&light_end.point)
+            * light_end.scattering(&camera_end.point)
+            * light_end.throughput
+            / distance_squared;
+        if contribution.length_squared() <= 0.0 {
+            return no_light;
+        }
+        let transmittance = transmittance(world, &camera_end.point, &light_end.point, time);
+        if transmittance <= 0.0 {
+            return no_light;
+        }
+        let weight = self.mis_weight(
+            camera,
+            settings,
+            camera_path,
+            light_path,
+            camera_end,
+            Some(light_end),
+            s,
+            t,
+            time,
+        );
+        weight * transmittance * contribution
+    }
+
+    // Power heuristic weight for the strategy joining `s` light vertices to `t` camera
+    // vertices. Rather than the densities of the path under every strategy, only their
+    // ratios are needed, found by walking out from the join along each subpath. The
+    // vertices at the ends of the subpaths are passed in, as they may have been sampled
+    // just for this strategy.
+    #[allow(clippy::too_many_arguments)]
+    fn mis_weight(
+        &self,
+        camera: &Camera,
+        settings: &RenderSettings,
+        camera_path: &[Vertex],
+        light_path: &[Vertex],
+        camera_end: &Vertex,
+        light_end: Option<&Vertex>,
+        s: usize,
+        t: usize,
+        time: f32,
+    ) -> f32 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        // The reverse densities of the vertices at and next to the join depend on how the
+        // subpaths were joined
+        let mut camera_end_forward = camera_end.pdf_forward;
+        let camera_end_reverse;
+        let mut camera_previous_reverse = 0.0;
+        let mut light_end_reverse = 0.0;
+        let mut light_previous_reverse = 0.0;
+        match light_end {
+            None => {
+                // The camera subpath ended on an emitter, which light subpaths could have
+                // started from
+                let (hit, in_ray) = match &camera_end.kind {
+                    VertexKind::Scatter { hit, in_ray } => (hit, in_ray),
+                    _ => return 1.0,
+                };
+                camera_end_reverse = light_origin_pdf(in_ray, settings);
+                // Emitters which aren't lights, such as glowing media, can only be found
+                // from the camera
+                if camera_end_reverse <= 0.0 {
+                    return 1.0;
+                }
+                camera_end_forward *= hit.normal.dot(&in_ray.direction.unit()).abs();
+                if t >= 3 {
+                    let previous = &camera_path[t - 2];
+                    let pdf = emission_pdf(hit, &(previous.point - camera_end.point));
+                    camera_previous_reverse = previous.convert_pdf(pdf, &camera_end.point);
+                }
+            }
+            Some(light_end) => {
+                camera_end_reverse = light_end.pdf(None, camera_end, camera, time);
+                if t >= 3 {
+                    camera_previous_reverse =
+                        camera_end.pdf(Some(light_end), &camera_path[t - 2], camera, time);
+                }
+                light_end_reverse = camera_end.pdf(None, light_end, camera, time);
+                if s >= 2 {
+                    light_previous_reverse =
+                        light_end.pdf(Some(camera_end), &light_path[s - 2], camera, time);
+                }
+            }
+        }
+
+        // Densities of zero mark specular bounces, whose ratios cancel
+        let remap = |pdf: f32| if pdf != 0.0 { pdf } else { 1.0 };
+        let mut sum = 0.0;
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            let (forward, reverse, specular) = if i == t - 1 {
+                (camera_end_forward, camera_end_reverse, false)
+            } else if i == t - 2 {
+                let vertex = &camera_path[i];
+                (vertex.pdf_forward, camera_previous_reverse, vertex.specular)
+            } else {
+                let vertex = &camera_path[i];
+                (vertex.pdf_forward, vertex.pdf_reverse, vertex.specular)
+            };
+            ratio *= remap(reverse) / remap(forward);
+            if !specular && !camera_path[i - 1].specular {
+                sum += ratio * ratio;
+            }
+        }
+        ratio = 1.0;
+        for i in (0..s).rev() {
+            let (forward, reverse, specular) = if i == s - 1 {
+                let light_end = light_end.expect("s is only non zero with a light end");
+                (light_end.pdf_forward, light_end_reverse, false)
+            } else if i == s - 2 {
+                let vertex = &light_path[i];
+                (vertex.pdf_forward, light_previous_reverse, vertex.specular)
+            } else {
+                let vertex = &light_path[i];
+                (vertex.pdf_forward, vertex.pdf_reverse, vertex.specular)
+            };
+            ratio *= remap(reverse) / remap(forward);
+            let previous_specular = i > 0 && light_path[i - 1].specular;
+            if !specular && !previous_specular {
+                sum += ratio * ratio;
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+}
+
+// Picks a point on one of the lights at random, as the start of a light subpath
+fn sample_light(settings: &RenderSettings) -> Option<Vertex> {
+    let sample = sample_light_surface(&settings.lights)?;
+    let emitted = sample.hit.material.emitted(&sample.hit);
+    if emitted.length_squared() <= 0.0 {
+        return None;
+    }
+    Some(Vertex {
+        point: sample.hit.point,
+        kind: VertexKind::Light(sample.hit),
+        throughput: emitted / sample.pdf,
+        specular: false,
+        pdf_forward: sample.pdf,
+        pdf_reverse: 0.0,
+    })
+}
+
+// Density per unit area with which sample_light picks the point the ray hits
+fn light_origin_pdf(ray: &Ray, settings: &RenderSettings) -> f32 {
+    if settings.lights.is_empty() {
+        return 0.0;
+    }
+    let total: f32 = settings
+        .lights
+        .iter()
+        .map(|light| light.surface_pdf(ray))
+        .sum();
+    total / settings.lights.len() as f32
+}
+
+// Stopping short of both ends keeps either from shadowing itself
+fn transmittance(world: &dyn Hitable, from: &Vec3, to: &Vec3, time: f32) -> f32 {
+    let offset = *to - *from;
+    let distance = offset.length();
+    let ray = Ray::new(*from, offset / distance, time);
+    world.transmittance(&ray, 0.0001, distance - 0.0001)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rand::Rng;
+
+    use super::BidirectionalPathTracer;
+    use crate::{
+        integrator::{
+            tests::{assert_converges, cornell_box, Scene},
+            Integrator,
+        },
+        integrators::PathTracer,
+        materials::{Diffuse, DiffuseLight},
+        random,
+        shapes::XzRect,
+        structures::Vec3,
+        Hitable, Splats,
+    };
+
+    #[test]
+    fn converges_to_the_path_tracer() {
+        let scene = cornell_box();
+        let path_traced = scene.render(PathTracer::arc(3, 6), 1024, 1);
+        let bidirectional = scene.render(BidirectionalPathTracer::arc(3, 6), 1024, 2);
+        assert_converges(&path_traced, &bidirectional, 0.05, 2, 0.15);
+    }
+
+    #[test]
+    fn splats_light_paths_onto_the_pixels_they_reach() {
+        const SIZE: usize = 8;
+        // A white patch on a black floor, so only light paths bouncing off the patch can
+        // be joined to the camera
+        let on_patch =
+            |point: Vec3| (0.2..=0.8).contains(&point.x()) && (-0.8..=-0.2).contains(&point.z());
+        let light = XzRect::arc(
+            -0.5,
+            0.5,
+            -0.5,
+            0.5,
+            2.0,
+            DiffuseLight::arc(Vec3::new(4.0, 4.0, 4.0)),
+        );
+        let shapes: Vec<Arc<dyn Hitable>> = vec![
+            XzRect::arc(
+                -5.0,
+                5.0,
+                -5.0,
+                5.0,
+                0.0,
+                Diffuse::arc(Vec3::new(0.0, 0.0, 0.0)),
+            ),
+            XzRect::arc(
+                0.2,
+                0.8,
+                -0.8,
+                -0.2,
+                0.001,
+                Diffuse::arc(Vec3::new(1.0, 1.0, 1.0)),
+            ),
+            light.clone(),
+        ];
+        let mut scene = Scene::new(
+            &shapes,
+            Vec3::new(0.0, 1.5, 1.5),
+            Vec3::new(0.0, 0.0, 0.0),
+            40.0,
+            SIZE,
+            SIZE,
+        );
+        scene.lights.push(light);
+        let integrator = BidirectionalPathTracer::new(3, 6);
+        let settings = scene.settings(BidirectionalPathTracer::arc(3, 6), 1, 1);
+
+        random::reseed(1);
+        let splats = Splats::new(SIZE, SIZE);
+        for _ in 0..4096 {
+            let ray = scene
+                .camera
+                .get_ray(random::rng().gen(), random::rng().gen());
+            integrator.radiance(&ray, &scene.world, &scene.camera, &settings, &splats);
+        }
+        let splatted = splats.framebuffer(1.0);
+
+        // Whether the ray through the centre of each pixel lands on the patch, with pixels
+        // counted down from the top as splats are
+        let sees_patch = |column: usize, row: usize| {
+            let ray = scene.camera.get_ray(
+                (column as f32 + 0.5) / SIZE as f32,
+                (SIZE - row) as f32 / SIZE as f32 + 0.5 / SIZE as f32,
+            );
+            scene
+                .world
+                .hit(&ray, 0.0001, f32::MAX)
+                .is_some_and(|hit| on_patch(hit.point))
+        };
+        let mut patch_pixels = 0;
+        for row in 0..SIZE {
+            for column in 0..SIZE {
+                let splat = splatted.pixel(column, row);
+                if sees_patch(column, row) {
+                    patch_pixels += 1;
+                    assert!(
+                        splat.length_squared() > 0.0,
+                        "pixel {}, {} sees the patch but has no light",
+                        column,
+                        row
+                    );
+                } else if splat.length_squared() > 0.0 {
+                    // Pixels on the patch's edge may see it away from their centres
+                    let near_patch = (row.saturating_sub(1)..=(row + 1).min(SIZE - 1)).any(|y| {
+                        (column.saturating_sub(1)..=(column + 1).min(SIZE - 1))
+                            .any(|x| sees_patch(x, y))
+                    });
+                    assert!(
+                        near_patch,
+                        "pixel {}, {} has light but doesn't see the patch",
+                        column, row
+                    );
+                }
+            }
+        }
+        assert!(patch_pixels > 0);
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use super::display;
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Heat map of the bounding box and primitive tests made tracing each camera ray, from
+/// blue for none through green and yellow to red at `max_cost` or more.
+pub struct BvhCost {
+    max_cost: usize,
+}
+
+impl BvhCost {
+    pub fn new(max_cost: usize) -> Self {
+        Self { max_cost }
+    }
+
+    pub fn arc(max_cost: usize) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(max_cost))
+    }
+}
+
+impl Integrator for BvhCost {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        _settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let cost = world.hit_cost(ray, 0.0001, f32::MAX);
+        let heat = (cost as f32 / self.max_cost.max(1) as f32).min(1.0);
+        display(heat_ramp(heat))
+    }
+}
+
+fn heat_ramp(heat: f32) -> Vec3 {
+    let stops = [
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(1.0, 1.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+    ];
+    let position = heat * (stops.len() - 1) as f32;
+    let index = (position as usize).min(stops.len() - 2);
+    let t = position - index as f32;
+    (1.0 - t) * stops[index] + t * stops[index + 1]
+}
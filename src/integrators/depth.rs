@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use super::display;
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Distance from the camera as a grey level, white up close and fading to black at
+/// `max_distance`.
+pub struct Depth {
+    max_distance: f32,
+}
+
+impl Depth {
+    pub fn new(max_distance: f32) -> Self {
+        Self { max_distance }
+    }
+
+    pub fn arc(max_distance: f32) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(max_distance))
+    }
+}
+
+impl Integrator for Depth {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        _settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let hit = match world.hit(ray, 0.0001, f32::MAX) {
+            Some(hit) => hit,
+            None => return Vec3::new(0.0, 0.0, 0.0),
+        };
+        // Camera rays aren't unit length, so the hit distance isn't in world units
+        let distance = (hit.point - ray.origin).length();
+        let brightness = (1.0 - distance / self.max_distance).clamp(0.0, 1.0);
+        display(Vec3::new(brightness, brightness, brightness))
+    }
+}
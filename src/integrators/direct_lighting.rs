@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use super::lighting::{emitted_light, environment_light, sample_environment, sample_lights};
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Light reaching the first diffuse or glossy surface straight from an emitter or the
+/// environment, with no indirect bounces. Mirrors and glass are followed, up to
+/// `max_depth` of them, so lights are still seen through them.
+pub struct DirectLighting {
+    max_depth: u32,
+}
+
+impl DirectLighting {
+    pub fn new(max_depth: u32) -> Self {
+        Self { max_depth }
+    }
+
+    pub fn arc(max_depth: u32) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(max_depth))
+    }
+}
+
+impl Integrator for DirectLighting {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let mut colour = Vec3::new(0.0, 0.0, 0.0);
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        let mut scatter_pdf = 0.0;
+        // Set once the path has scattered off a non-specular surface, after which only
+        // the emitter or environment it lands on is counted
+        let mut last_bounce = false;
+        let mut depth = 0;
+        loop {
+            let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+                Some(hit) => hit,
+                None => {
+                    return colour + throughput * environment_light(&ray, scatter_pdf, settings)
+                }
+            };
+            colour += throughput * emitted_light(&ray, &hit, scatter_pdf, settings);
+            if last_bounce || depth >= self.max_depth {
+                return colour;
+            }
+
+            colour += throughput * sample_environment(&ray, &hit, world, settings);
+            colour += throughput * sample_lights(&ray, &hit, world, settings);
+            let sample = match hit.material.sample(&ray, &hit) {
+                Some(sample) => sample,
+                None => return colour,
+            };
+            throughput *= sample.weight;
+            scatter_pdf = if sample.specular { 0.0 } else { sample.pdf };
+            last_bounce = !sample.specular;
+            ray = Ray::new(hit.point, sample.direction, ray.time);
+            depth += 1;
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::{f32::consts, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    hitable::{Hitable, RayHit, SurfaceSample},
+    random,
+    renderer::RenderSettings,
+    structures::{Ray, Vec3},
+};
+
+// Light from the environment found by a ray which missed everything, weighted against
+// finding the same light with sample_environment. A zero scatter_pdf means the ray could
+// not have been found that way.
+pub(crate) fn environment_light(ray: &Ray, scatter_pdf: f32, settings: &RenderSettings) -> Vec3 {
+    let mut environment = settings.environment.colour(&ray.direction);
+    if scatter_pdf > 0.0 {
+        let light_pdf = settings.environment.pdf(&ray.direction);
+        environment *= power_heuristic(scatter_pdf, light_pdf);
+    }
+    environment
+}
+
+// Light emitted by the surface a ray hit, weighted against finding the same light with
+// sample_lights
+pub(crate) fn emitted_light(
+    ray: &Ray,
+    hit: &RayHit,
+    scatter_pdf: f32,
+    settings: &RenderSettings,
+) -> Vec3 {
+    let mut emitted = hit.material.emitted(hit);
+    if scatter_pdf > 0.0 && emitted.length_squared() > 0.0 {
+        emitted *= power_heuristic(scatter_pdf, light_pdf(ray, settings));
+    }
+    emitted
+}
+
+// Light arriving directly from the environment, chosen by importance sampling the
+// environment and weighted against finding the same light by scattering.
+pub(crate) fn sample_environment(
+    ray: &Ray,
+    hit: &RayHit,
+    hitable: &dyn Hitable,
+    settings: &RenderSettings,
+) -> Vec3 {
+    let no_light = Vec3::new(0.0, 0.0, 0.0);
+    let sample = match settings.environment.sample() {
+        Some(sample) => sample,
+        None => return no_light,
+    };
+    let attenuation = hit.material.eval(ray, hit, &sample.direction);
+    if attenuation.length_squared() <= 0.0 {
+        return no_light;
+    }
+    let shadow_ray = Ray::new(hit.point, sample.direction, ray.time);
+    let transmittance = hitable.transmittance(&shadow_ray, 0.0001, f32::MAX);
+    if transmittance <= 0.0 {
+        return no_light;
+    }
+    let scatter_pdf = hit.material.pdf(ray, hit, &sample.direction);
+    let weight = power_heuristic(sample.pdf, scatter_pdf) / sample.pdf;
+    weight * transmittance * attenuation * settings.environment.colour(&sample.direction)
+}
+
+// Light arriving directly from one of the lights, picked at random, and weighted against
+// finding the same light by scattering.
+pub(crate) fn sample_lights(
+    ray: &Ray,
+    hit: &RayHit,
+    hitable: &dyn Hitable,
+    settings: &RenderSettings,
+) -> Vec3 {
+    let no_light = Vec3::new(0.0, 0.0, 0.0);
+    if settings.lights.is_empty() {
+        return no_light;
+    }
+    let light = &settings.lights[random::rng().gen_range(0..settings.lights.len())];
+    let direction = match light.sample_direction(&hit.point) {
+        Some(direction) if direction.length_squared() > 0.0 => direction.unit(),
+        _ => return no_light,
+    };
+    let attenuation = hit.material.eval(ray, hit, &direction);
+    if attenuation.length_squared() <= 0.0 {
+        return no_light;
+    }
+    let light_ray = Ray::new(hit.point, direction, ray.time);
+    let light_hit = match light.hit(&light_ray, 0.0001, f32::MAX) {
+        Some(light_hit) => light_hit,
+        None => return no_light,
+    };
+    let pdf = light.direction_pdf(&light_ray) / settings.lights.len() as f32;
+    if pdf <= 0.0 {
+        return no_light;
+    }
+    // Stopping short of the light keeps it from shadowing itself
+    let transmittance = hitable.transmittance(&light_ray, 0.0001, light_hit.distance - 0.0001);
+    if transmittance <= 0.0 {
+        return no_light;
+    }
+    let scatter_pdf = hit.material.pdf(ray, hit, &direction);
+    let weight = power_heuristic(pdf, scatter_pdf) / pdf;
+    weight * transmittance * attenuation * light_hit.material.emitted(&light_hit)
+}
+
+// Density with which sample_lights would have chosen the ray's direction from its origin
+pub(crate) fn light_pdf(ray: &Ray, settings: &RenderSettings) -> f32 {
+    if settings.lights.is_empty() {
+        return 0.0;
+    }
+    let total: f32 = settings
+        .lights
+        .iter()
+        .map(|light| light.direction_pdf(ray))
+        .sum();
+    total / settings.lights.len() as f32
+}
+
+// Picks a point on one of the lights at random, with the density of picking it out of all
+// of them
+pub(crate) fn sample_light_surface(lights: &[Arc<dyn Hitable>]) -> Option<SurfaceSample> {
+    if lights.is_empty() {
+        return None;
+    }
+    let light = &lights[random::rng().gen_range(0..lights.len())];
+    let mut sample = light.sample_surface()?;
+    sample.pdf /= lights.len() as f32;
+    if sample.pdf <= 0.0 {
+        return None;
+    }
+    Some(sample)
+}
+
+// Picks a direction for light to leave a point on an emitter. Diffuse emitters shine from
+// both sides, like the two sided shapes they are usually on, so either side is picked and
+// then a cosine weighted direction
+pub(crate) fn sample_emission(hit: &RayHit) -> Vec3 {
+    let side = if random::rng().gen::<bool>() {
+        hit.normal
+    } else {
+        -hit.normal
+    };
+    let direction = side + Vec3::get_point_on_unit_sphere();
+    if direction.length_squared() < 1e-8 {
+        side
+    } else {
+        direction
+    }
+}
+
+// Density per unit solid angle of sample_emission choosing `direction`
+pub(crate) fn emission_pdf(hit: &RayHit, direction: &Vec3) -> f32 {
+    hit.normal.dot(&direction.unit()).abs() / (2.0 * consts::PI)
+}
+
+pub(crate) fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    pdf.powi(2) / (pdf.powi(2) + other_pdf.powi(2))
+}
@@ -0,0 +1,553 @@
+use std::{f64::consts, sync::Arc};
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::SmallRng,
+    Rng, SeedableRng,
+};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use super::PathTracer;
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    random::{self, Sampler},
+    renderer::{Framebuffer, RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+// Chance of each mutation drawing every number afresh, which lets chains leave the paths
+// they have found for unrelated ones
+const LARGE_STEP_PROBABILITY: f32 = 0.3;
+// Spread of the change made to each number by the other mutations, which explore the
+// paths close to the current one
+const SMALL_STEP_SIGMA: f64 = 0.01;
+
+/// Primary sample space Metropolis light transport. Paths are traced as by `PathTracer`,
+/// but with random numbers chosen by Markov chains, each mutating the numbers of its last
+/// path and keeping the new path in proportion to how bright it is. Having found a path
+/// which carries light through a small opening, a chain goes on to find others near it.
+/// Each path's light is splatted onto whichever pixel it passes through, so bright parts
+/// of the image get more paths than dark ones.
+pub struct Metropolis {
+    tracer: PathTracer,
+    // Paths traced from fresh numbers to estimate the brightness of the whole image, and
+    // to start the chains from
+    bootstrap_samples: usize,
+    chains: usize,
+}
+
+// The numbers a path was traced from, mutated lazily as each is drawn, so that paths
+// only ever pay for the numbers they use
+struct PrimarySamples {
+    rng: SmallRng,
+    samples: Vec<PrimarySample>,
+    // Number of samples drawn by the current path so far
+    index: usize,
+    iteration: u64,
+    large_step: bool,
+    // The latest iteration whose large step was accepted
+    last_large_step: u64,
+}
+
+struct PrimarySample {
+    value: f64,
+    // The iteration in which the value was last changed
+    modified: u64,
+    // The value and iteration before the current mutation, restored if it is rejected
+    backup: (f64, u64),
+}
+
+struct PathSample {
+    // Where the path leaves the image, as passed to `Camera::get_ray`
+    x: f32,
+    y: f32,
+    radiance: Vec3,
+    // The brightness the chains visit paths in proportion to
+    contribution: f32,
+}
+
+struct Chain {
+    samples: PrimarySamples,
+    current: PathSample,
+}
+
+impl Metropolis {
+    pub fn new(min_depth: u32, max_depth: u32, bootstrap_samples: usize, chains: usize) -> Self {
+        assert!(
+            bootstrap_samples > 0,
+            "Metropolis light transport needs at least one bootstrap sample"
+        );
+        assert!(
+            chains > 0,
+            "Metropolis light transport needs at least one chain"
+        );
+        Self {
+            tracer: PathTracer::new(min_depth, max_depth),
+            bootstrap_samples,
+            chains,
+        }
+    }
+
+    pub fn arc(
+        min_depth: u32,
+        max_depth: u32,
+        bootstrap_samples: usize,
+        chains: usize,
+    ) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(min_depth, max_depth, bootstrap_samples, chains))
+    }
+
+    /// Renders the image with as many mutations as `settings` asks for samples, split into
+    /// `rounds` rounds. `progress` is passed the image so far after every round but the
+    /// last, whose image is returned.
+    pub fn render_progressively(
+        &self,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        rounds: usize,
+        mut progress: impl FnMut(&Framebuffer),
+    ) -> Framebuffer {
+        let splats = Splats::new(settings.width, settings.height);
+        let contributions: Vec<f32> = (0..self.bootstrap_samples)
+            .into_par_iter()
+            .map(|index| {
+                let samples = PrimarySamples::new(bootstrap_seed(settings.seed, index));
+                self.trace(samples, world, camera, settings).1.contribution
+            })
+            .collect();
+        // The chains only find paths in proportion to their brightness, so the image's
+        // overall brightness has to come from paths traced independently
+        let brightness = contributions
+            .iter()
+            .map(|&contribution| contribution as f64)
+            .sum::<f64>()
+            / self.bootstrap_samples as f64;
+        let starts = match WeightedIndex::new(&contributions) {
+            Ok(starts) => starts,
+            // None of the paths found any light
+            Err(_) => return splats.framebuffer(0.0),
+        };
+
+        // Starting each chain from a bootstrap path picked in proportion to its brightness
+        // means the chains need no time to settle before their paths can be counted
+        let mut rng = SmallRng::seed_from_u64(settings.seed);
+        let picked: Vec<usize> = (0..self.chains).map(|_| starts.sample(&mut rng)).collect();
+        let mut chains: Vec<Chain> = picked
+            .into_par_iter()
+            .map(|index| {
+                let samples = PrimarySamples::new(bootstrap_seed(settings.seed, index));
+                let (samples, current) = self.trace(samples, world, camera, settings);
+                Chain { samples, current }
+            })
+            .collect();
+
+        let mutations = settings.width * settings.height * settings.samples;
+        let rounds = rounds.max(1);
+        // The mutations of each chain made by the end of a round
+        let made = |chain: usize, round: usize| {
+            let total = mutations / self.chains + usize::from(chain < mutations % self.chains);
+            total * round / rounds
+        };
+        let mut scale = 0.0;
+        for round in 1..=rounds {
+            chains = chains
+                .into_par_iter()
+                .enumerate()
+                .map(|(index, mut chain)| {
+                    for _ in made(index, round - 1)..made(index, round) {
+                        chain = self.mutate(chain, world, camera, settings, &splats);
+                    }
+                    chain
+                })
+                .collect();
+            let mutations_made: usize = (0..self.chains).map(|chain| made(chain, round)).sum();
+            if mutations_made > 0 {
+                // Each mutation splats a total weight of one, spread over the image
+                scale = (brightness * (settings.width * settings.height) as f64
+                    / mutations_made as f64) as f32;
+            }
+            if round < rounds {
+                progress(&splats.framebuffer(scale));
+            }
+        }
+        splats.framebuffer(scale)
+    }
+
+    fn trace(
+        &self,
+        samples: PrimarySamples,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+    ) -> (PrimarySamples, PathSample) {
+        random::with_sampler(samples, || {
+            let mut rng = random::rng();
+            let x: f32 = rng.gen();
+            // Offset by a row as in render, so the image lines up with other integrators'
+            let y = rng.gen::<f32>() + 1.0 / settings.height as f32;
+            let ray = camera.get_ray(x, y);
+            // Path tracing never splats
+            let radiance = self.tracer.radiance(
+                &ray,
+                world,
+                camera,
+                settings,
+                &Splats::new(settings.width, settings.height),
+            );
+            let contribution = luminance(&radiance);
+            PathSample {
+                x,
+                y,
+                radiance,
+                contribution: if contribution.is_finite() && contribution > 0.0 {
+                    contribution
+                } else {
+                    0.0
+                },
+            }
+        })
+    }
+
+    fn mutate(
+        &self,
+        chain: Chain,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        splats: &Splats,
+    ) -> Chain {
+        let Chain {
+            mut samples,
+            current,
+        } = chain;
+        samples.start_iteration();
+        let (mut samples, proposed) = self.trace(samples, world, camera, settings);
+        let acceptance = if proposed.contribution <= 0.0 {
+            0.0
+        } else if current.contribution <= 0.0 {
+            1.0
+        } else {
+            (proposed.contribution / current.contribution).min(1.0)
+        };
+        // Both paths are splatted, weighted by the chance of the chain moving to each,
+        // which is less noisy than only splatting the one it ends up on
+        if acceptance > 0.0 {
+            splats.add(
+                proposed.x,
+                proposed.y,
+                proposed.radiance * (acceptance / proposed.contribution),
+            );
+        }
+        if acceptance < 1.0 {
+            splats.add(
+                current.x,
+                current.y,
+                current.radiance * ((1.0 - acceptance) / current.contribution),
+            );
+        }
+        if samples.rng.gen::<f32>() < acceptance {
+            samples.accept();
+            Chain {
+                samples,
+                current: proposed,
+            }
+        } else {
+            samples.reject();
+            Chain { samples, current }
+        }
+    }
+}
+
+impl Integrator for Metropolis {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+        splats: &Splats,
+    ) -> Vec3 {
+        self.tracer.radiance(ray, world, camera, settings, splats)
+    }
+
+    fn render(
+        &self,
+        world: &dyn Hitable,
+        camera: &Camera,
+        settings: &RenderSettings,
+    ) -> Option<Framebuffer> {
+        Some(self.render_progressively(world, camera, settings, 1, |_| {}))
+    }
+}
+
+impl PrimarySamples {
+    // The first path traced draws every number afresh, so is the same path as a bootstrap
+    // sample with the same seed
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: SmallRng::seed_from_u64(seed),
+            samples: Vec::new(),
+            index: 0,
+            iteration: 0,
+            large_step: true,
+            last_large_step: 0,
+        }
+    }
+
+    fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<f32>() < LARGE_STEP_PROBABILITY;
+        self.index = 0;
+    }
+
+    fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.modified == self.iteration {
+                (sample.value, sample.modified) = sample.backup;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    fn next(&mut self) -> f64 {
+        if self.index == self.samples.len() {
+            // Numbers no path has drawn yet are as good as drawn afresh
+            self.samples.push(PrimarySample {
+                value: self.rng.gen(),
+                modified: self.iteration,
+                backup: (0.0, 0),
+            });
+        }
+        let sample = &mut self.samples[self.index];
+        self.index += 1;
+        // Numbers left out of accepted large steps, by paths which didn't draw them, are
+        // replaced as those steps would have done
+        if sample.modified < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.modified = self.last_large_step;
+        }
+        sample.backup = (sample.value, sample.modified);
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            // Catches up on the small steps missed while the number wasn't drawn, whose
+            // spreads add up as the square root of their count
+            let steps = (self.iteration - sample.modified) as f64;
+            let value = sample.value + SMALL_STEP_SIGMA * steps.sqrt() * normal(&mut self.rng);
+            sample.value = value - value.floor();
+            if sample.value >= 1.0 {
+                sample.value = 0.0;
+            }
+        }
+        sample.modified = self.iteration;
+        sample.value
+    }
+}
+
+impl Sampler for PrimarySamples {
+    fn next_u32(&mut self) -> u32 {
+        (self.next() * 2f64.powi(32)) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (self.next() * 2f64.powi(64)) as u64
+    }
+}
+
+fn bootstrap_seed(seed: u64, index: usize) -> u64 {
+    seed ^ (index as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+// A normally distributed number, by the Box-Muller transform
+fn normal(rng: &mut SmallRng) -> f64 {
+    (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt() * (2.0 * consts::PI * rng.gen::<f64>()).cos()
+}
+
+fn luminance(colour: &Vec3) -> f32 {
+    0.2126 * colour.r() + 0.7152 * colour.g() + 0.0722 * colour.b()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{bootstrap_seed, luminance, Chain, Metropolis, PrimarySamples};
+    use crate::{
+        integrator::tests::{assert_converges, cornell_box, Scene},
+        integrators::PathTracer,
+        materials::{Diffuse, DiffuseLight},
+        shapes::Sphere,
+        structures::Vec3,
+        Framebuffer, Hitable, Splats,
+    };
+
+    #[test]
+    fn converges_to_the_path_tracer() {
+        // A diffuse ball on a floor, lit by a small ball of light above it and out of
+        // view, so that the chains spread out over the whole image
+        let light = Sphere::arc(
+            Vec3::new(1.5, 4.5, 0.0),
+            0.5,
+            DiffuseLight::arc(Vec3::new(12.0, 12.0, 12.0)),
+        );
+        let shapes: Vec<Arc<dyn Hitable>> = vec![
+            Sphere::arc(
+                Vec3::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Diffuse::arc(Vec3::new(0.5, 0.5, 0.5)),
+            ),
+            Sphere::arc(
+                Vec3::new(0.0, 1.0, 0.0),
+                1.0,
+                Diffuse::arc(Vec3::new(0.7, 0.3, 0.2)),
+            ),
+            light.clone(),
+        ];
+        let mut scene = Scene::new(
+            &shapes,
+            Vec3::new(0.0, 2.0, 6.0),
+            Vec3::new(0.0, 0.8, 0.0),
+            45.0,
+            12,
+            12,
+        );
+        scene.lights.push(light);
+
+        let path_traced = scene.render(PathTracer::arc(3, 6), 512, 1);
+        let metropolis = scene.render(Metropolis::arc(3, 6, 20_000, 64), 256, 2);
+        assert_converges(&path_traced, &metropolis, 0.05, 3, 0.15);
+    }
+
+    #[test]
+    fn renders_progressively() {
+        let scene = cornell_box();
+        let settings = scene.settings(PathTracer::arc(3, 6), 64, 1);
+        let metropolis = Metropolis::new(3, 6, 2000, 16);
+        let render = |rounds, progress: &mut dyn FnMut(&Framebuffer)| {
+            metropolis.render_progressively(
+                &scene.world,
+                &scene.camera,
+                &settings,
+                rounds,
+                progress,
+            )
+        };
+        let mut frames = Vec::new();
+        let last = render(4, &mut |framebuffer| {
+            frames.push(framebuffer.pixels().to_vec())
+        });
+        let whole = render(1, &mut |_| {
+            panic!("A single round has no frames before the last")
+        });
+
+        // Every frame is scaled to the brightness estimated from the bootstrap paths, but
+        // the later ones have more paths in them
+        let brightness = |pixels: &[Vec3]| pixels.iter().map(luminance).sum::<f32>();
+        let expected = brightness(last.pixels());
+        assert_eq!(frames.len(), 3);
+        for frame in &frames {
+            let actual = brightness(frame);
+            assert!(
+                (actual - expected).abs() <= 1e-3 * expected,
+                "frame brightness is {}, expected {}",
+                actual,
+                expected
+            );
+            assert!(frame
+                .iter()
+                .zip(last.pixels())
+                .any(|(frame, last)| frame.r() != last.r()));
+        }
+        // The chains make the same mutations however they are split into rounds, so only
+        // the order the splats are added in differs
+        for (whole, last) in whole.pixels().iter().zip(last.pixels()) {
+            for channel in 0..3 {
+                assert!((whole[channel] - last[channel]).abs() <= 1e-4 * whole[channel] + 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn rejected_mutations_restore_the_samples() {
+        let mut samples = PrimarySamples::new(1);
+        let drawn: Vec<f64> = (0..8).map(|_| samples.next()).collect();
+        let mutate = |samples: &mut PrimarySamples| {
+            samples.start_iteration();
+            samples.large_step = false;
+            let mutated: Vec<f64> = (0..8).map(|_| samples.next()).collect();
+            for (before, after) in samples
+                .samples
+                .iter()
+                .map(|sample| sample.backup.0)
+                .zip(&mutated)
+            {
+                // Small steps move each number a little, wrapping around at the ends
+                let change = (after - before).abs();
+                assert!(change > 0.0 && change.min(1.0 - change) < 0.1);
+            }
+            mutated
+        };
+        let values = |samples: &PrimarySamples| -> Vec<f64> {
+            samples.samples.iter().map(|sample| sample.value).collect()
+        };
+
+        mutate(&mut samples);
+        samples.reject();
+        assert_eq!(values(&samples), drawn);
+        assert_eq!(samples.iteration, 0);
+
+        let mutated = mutate(&mut samples);
+        samples.accept();
+        assert_eq!(values(&samples), mutated);
+        assert_eq!(samples.iteration, 1);
+    }
+
+    #[test]
+    fn mutations_splat_a_total_brightness_of_one() {
+        const MUTATIONS: usize = 1000;
+        let scene = cornell_box();
+        let settings = scene.settings(PathTracer::arc(3, 6), 1, 1);
+        let metropolis = Metropolis::new(3, 6, 1, 1);
+        let trace = |samples| metropolis.trace(samples, &scene.world, &scene.camera, &settings);
+        let (samples, current) = (0..)
+            .map(|index| trace(PrimarySamples::new(bootstrap_seed(1, index))))
+            .find(|(_, current)| current.contribution > 0.0)
+            .unwrap();
+
+        let splats = Splats::new(scene.width, scene.height);
+        let mut chain = Chain { samples, current };
+        let mut moves = 0;
+        for _ in 0..MUTATIONS {
+            let previous = (chain.current.x, chain.current.y);
+            chain = metropolis.mutate(chain, &scene.world, &scene.camera, &settings, &splats);
+            // Paths carrying no light are never accepted
+            assert!(chain.current.contribution > 0.0);
+            if (chain.current.x, chain.current.y) != previous {
+                moves += 1;
+            }
+        }
+        // Proposals dimmer than the current path are only sometimes accepted
+        assert!(
+            moves > 0 && moves < MUTATIONS,
+            "the chain moved {} times",
+            moves
+        );
+        // Each mutation splats both paths, weighted by the chance of moving to each
+        let total: f32 = splats.framebuffer(1.0).pixels().iter().map(luminance).sum();
+        assert!(
+            (total - MUTATIONS as f32).abs() <= 1e-3 * MUTATIONS as f32,
+            "total brightness is {}, expected {}",
+            total,
+            MUTATIONS
+        );
+    }
+}
@@ -0,0 +1,50 @@
+mod ambient_occlusion;
+mod bidirectional;
+mod bvh_cost;
+mod depth;
+mod direct_lighting;
+mod lighting;
+mod metropolis;
+mod normals;
+mod path_tracer;
+mod photon_map;
+mod photon_mapper;
+
+pub use ambient_occlusion::*;
+pub use bidirectional::*;
+pub use bvh_cost::*;
+pub use depth::*;
+pub use direct_lighting::*;
+pub use metropolis::*;
+pub use normals::*;
+pub use path_tracer::*;
+pub use photon_map::*;
+pub use photon_mapper::*;
+
+use rand::Rng;
+
+use crate::{random, structures::Vec3};
+
+// Keeps even the brightest paths from bouncing forever inside closed glass
+const MAX_SURVIVAL_PROBABILITY: f32 = 0.95;
+
+// Ends paths carrying little light at random, returning false, and has the survivors carry
+// more to make up for them, so the image is not biased towards darkness
+fn survives_roulette(throughput: &mut Vec3) -> bool {
+    let survival = throughput
+        .x()
+        .max(throughput.y())
+        .max(throughput.z())
+        .min(MAX_SURVIVAL_PROBABILITY);
+    if random::rng().gen::<f32>() >= survival {
+        return false;
+    }
+    *throughput /= survival;
+    true
+}
+
+// Squares each channel, so debug colours are shown as given once the framebuffer's
+// gamma correction is applied
+fn display(colour: Vec3) -> Vec3 {
+    colour * colour
+}
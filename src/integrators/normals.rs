@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use super::display;
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Surface normals mapped from -1..1 to colours from 0 to 1, as in the first book.
+/// Rays which miss everything are black.
+pub struct Normals;
+
+impl Normals {
+    pub fn arc() -> Arc<dyn Integrator> {
+        Arc::new(Self)
+    }
+}
+
+impl Integrator for Normals {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        _settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        match world.hit(ray, 0.0001, f32::MAX) {
+            Some(hit) => display(0.5 * (hit.normal + Vec3::new(1.0, 1.0, 1.0))),
+            None => Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use super::{
+    lighting::{emitted_light, environment_light, sample_environment, sample_lights},
+    survives_roulette,
+};
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Unidirectional path tracing, sampling the lights and the environment at every bounce.
+pub struct PathTracer {
+    // Bounces after which paths may be ended by Russian roulette
+    min_depth: u32,
+    // Bounces after which paths are always ended
+    max_depth: u32,
+}
+
+impl PathTracer {
+    pub fn new(min_depth: u32, max_depth: u32) -> Self {
+        Self {
+            min_depth,
+            max_depth,
+        }
+    }
+
+    pub fn arc(min_depth: u32, max_depth: u32) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(min_depth, max_depth))
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let mut colour = Vec3::new(0.0, 0.0, 0.0);
+        // The fraction of light arriving along the current ray which reaches the camera
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // The density with which the previous bounce chose this ray, or zero if the ray
+        // could not also have been found by sampling the environment or the lights
+        let mut scatter_pdf = 0.0;
+        let mut depth = 0;
+        loop {
+            let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+                Some(hit) => hit,
+                None => {
+                    return colour + throughput * environment_light(&ray, scatter_pdf, settings)
+                }
+            };
+            colour += throughput * emitted_light(&ray, &hit, scatter_pdf, settings);
+            if depth >= self.max_depth {
+                return colour;
+            }
+
+            colour += throughput * sample_environment(&ray, &hit, world, settings);
+            colour += throughput * sample_lights(&ray, &hit, world, settings);
+            let sample = match hit.material.sample(&ray, &hit) {
+                Some(sample) => sample,
+                None => return colour,
+            };
+            throughput *= sample.weight;
+            // Specular paths can't be found by sampling lights, so aren't MIS weighted
+            scatter_pdf = if sample.specular { 0.0 } else { sample.pdf };
+            ray = Ray::new(hit.point, sample.direction, ray.time);
+            depth += 1;
+
+            if depth >= self.min_depth && !survives_roulette(&mut throughput) {
+                return colour;
+            }
+        }
+    }
+}
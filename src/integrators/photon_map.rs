@@ -0,0 +1,299 @@
+use std::{f32::consts, sync::Arc};
+
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use super::lighting::{emission_pdf, sample_emission, sample_light_surface};
+use crate::{
+    camera::Camera,
+    environment::Environment,
+    hitable::{Hitable, RayHit},
+    random,
+    structures::{KdTree, Ray, Vec3, AABB},
+};
+
+// Photons traced by each task, which draws from its own seeded sampler so the map is the
+// same however rayon schedules the work
+const PHOTONS_PER_TASK: usize = 4096;
+// Camera paths, on a grid across the image, traced to find the mirrors and glass which
+// photons from the environment are aimed at
+const CASTER_SEARCH_GRID: usize = 64;
+const CASTER_SEARCH_DEPTH: u32 = 4;
+// Samples of the environment and of the lights used to decide how many photons each sends
+const POWER_ESTIMATE_SAMPLES: usize = 256;
+// Photons averaged over by each estimate, where there are enough of them nearby
+const GATHER_COUNT: usize = 64;
+
+struct Photon {
+    // The direction the photon was travelling in when it landed
+    direction: Vec3,
+    power: Vec3,
+}
+
+/// Photons traced out from the lights and the environment which landed on a diffuse or
+/// glossy surface after passing through mirrors or glass, for estimating the caustics
+/// they focus there. Photons landing with no specular bounce are not stored, as path
+/// tracing finds that light easily.
+pub struct PhotonMap {
+    photons: KdTree<Photon>,
+    // Bounding sphere of the mirrors and glass found from the camera
+    casters: Option<(Vec3, f32)>,
+}
+
+impl PhotonMap {
+    /// Traces `count` photons, following each through up to `max_depth` specular
+    /// bounces. The environment surrounds everything, so its photons are aimed at the
+    /// mirrors and glass found by tracing paths from the camera, and only their caustics
+    /// are found.
+    pub fn caustics(
+        world: &dyn Hitable,
+        camera: &Camera,
+        environment: &dyn Environment,
+        lights: &[Arc<dyn Hitable>],
+        count: usize,
+        max_depth: u32,
+    ) -> Self {
+        let casters = find_casters(world, camera, max_depth);
+        let environment_share = environment_share(environment, lights, casters);
+        let seed: u64 = random::rng().gen();
+        let photons = (0..count.div_ceil(PHOTONS_PER_TASK))
+            .into_par_iter()
+            .flat_map_iter(|task| {
+                let sampler = SmallRng::seed_from_u64(
+                    seed ^ (task as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                );
+                let (_, photons) = random::with_sampler(sampler, || {
+                    let first = task * PHOTONS_PER_TASK;
+                    let mut photons = Vec::new();
+                    for _ in first..count.min(first + PHOTONS_PER_TASK) {
+                        let time = camera.sample_time();
+                        let photon = if random::rng().gen::<f32>() < environment_share {
+                            emit_from_environment(world, environment, casters, time)
+                                .map(|(ray, power)| (ray, power / environment_share))
+                        } else {
+                            emit_from_light(lights, time)
+                                .map(|(ray, power)| (ray, power / (1.0 - environment_share)))
+                        };
+                        if let Some((ray, power)) = photon {
+                            trace(world, ray, power / count as f32, max_depth, &mut photons);
+                        }
+                    }
+                    photons
+                });
+                photons
+            })
+            .collect();
+        Self {
+            photons: KdTree::new(photons),
+            casters,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    /// Radius of the sphere around the mirrors and glass found from the camera, or zero if
+    /// none were found.
+    pub fn caster_radius(&self) -> f32 {
+        self.casters.map_or(0.0, |(_, radius)| radius)
+    }
+
+    /// Light focused onto the hit by mirrors and glass, leaving back along the ray,
+    /// estimated from the density of the photons which landed within `radius` of it.
+    pub fn radiance(&self, ray: &Ray, hit: &RayHit, radius: f32) -> Vec3 {
+        let no_light = Vec3::new(0.0, 0.0, 0.0);
+        let photons = self.photons.nearest(&hit.point, GATHER_COUNT, radius);
+        // Where photons are dense the estimate only covers as far as the furthest one
+        // gathered, which keeps the edges of caustics sharp
+        let radius_squared = match photons.last() {
+            Some((distance_squared, _)) if photons.len() == GATHER_COUNT => *distance_squared,
+            Some(_) => radius * radius,
+            None => return no_light,
+        };
+        if radius_squared <= 0.0 {
+            return no_light;
+        }
+        let mut total = no_light;
+        for (_, photon) in photons {
+            let cosine = hit.normal.dot(&photon.direction).abs();
+            if cosine <= 0.0 {
+                continue;
+            }
+            // The photon density is per unit area, so already allows for the cosine which
+            // eval includes
+            total += hit.material.eval(ray, hit, &-photon.direction) * photon.power / cosine;
+        }
+        total / (consts::PI * radius_squared)
+    }
+}
+
+// Bounds the mirrors and glass which camera paths reach, whether seen directly or from
+// other surfaces, as those are the ones whose caustics can be seen
+fn find_casters(world: &dyn Hitable, camera: &Camera, max_depth: u32) -> Option<(Vec3, f32)> {
+    let mut rng = random::rng();
+    let mut bounds: Option<AABB> = None;
+    for i in 0..CASTER_SEARCH_GRID * CASTER_SEARCH_GRID {
+        let x = ((i % CASTER_SEARCH_GRID) as f32 + rng.gen::<f32>()) / CASTER_SEARCH_GRID as f32;
+        let y = ((i / CASTER_SEARCH_GRID) as f32 + rng.gen::<f32>()) / CASTER_SEARCH_GRID as f32;
+        let mut ray = camera.get_ray(x, y);
+        for _ in 0..=CASTER_SEARCH_DEPTH.min(max_depth) {
+            let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+                Some(hit) => hit,
+                None => break,
+            };
+            let sample = match hit.material.sample(&ray, &hit) {
+                Some(sample) => sample,
+                None => break,
+            };
+            if sample.specular {
+                let point = AABB::new(hit.point, hit.point);
+                bounds = Some(match bounds {
+                    Some(bounds) => AABB::surrounding_box(&bounds, &point),
+                    None => point,
+                });
+            }
+            ray = Ray::new(hit.point, sample.direction, ray.time);
+        }
+    }
+    let bounds = bounds?;
+    let radius = 0.5 * (bounds.max - bounds.min).length();
+    if radius > 0.0 {
+        Some((bounds.centre(), radius))
+    } else {
+        None
+    }
+}
+
+// The fraction of photons to start from the environment rather than the lights, in
+// proportion to the power each sends out, estimated from a few samples of each
+fn environment_share(
+    environment: &dyn Environment,
+    lights: &[Arc<dyn Hitable>],
+    casters: Option<(Vec3, f32)>,
+) -> f32 {
+    let radius = match casters {
+        Some((_, radius)) => radius,
+        None => return 0.0,
+    };
+    let brightness = |colour: Vec3| (colour.r() + colour.g() + colour.b()) / 3.0;
+    let mut environment_power = 0.0;
+    let mut light_power = 0.0;
+    for _ in 0..POWER_ESTIMATE_SAMPLES {
+        let direction = Vec3::get_point_on_unit_sphere();
+        // Light from every direction crossing a disc the size of the sphere
+        environment_power +=
+            brightness(environment.colour(&direction)) * 4.0 * consts::PI.powi(2) * radius.powi(2);
+        if let Some(sample) = sample_light_surface(lights) {
+            // Diffuse emitters shine from both sides
+            light_power += brightness(sample.hit.material.emitted(&sample.hit)) * 2.0 * consts::PI
+                / sample.pdf;
+        }
+    }
+    if environment_power <= 0.0 {
+        0.0
+    } else {
+        environment_power / (environment_power + light_power)
+    }
+}
+
+// Returns None where casters is None, when there is nothing to aim at
+fn emit_from_environment(
+    world: &dyn Hitable,
+    environment: &dyn Environment,
+    casters: Option<(Vec3, f32)>,
+    time: f32,
+) -> Option<(Ray, Vec3)> {
+    let (centre, radius) = casters?;
+    let mut rng = random::rng();
+    let (direction, pdf) = match environment.sample() {
+        Some(sample) => (sample.direction.unit(), sample.pdf),
+        None => (Vec3::get_point_on_unit_sphere(), 1.0 / (4.0 * consts::PI)),
+    };
+    if pdf <= 0.0 {
+        return None;
+    }
+    // Every ray from the direction which passes through the sphere crosses the disc facing
+    // the direction just outside it, so photons start at a point picked uniformly on it
+    let other = if direction.x().abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let u = direction.cross(&other).unit();
+    let v = direction.cross(&u);
+    let distance = rng.gen::<f32>().sqrt();
+    let angle = 2.0 * consts::PI * rng.gen::<f32>();
+    let origin = centre + radius * (direction + distance * (angle.cos() * u + angle.sin() * v));
+    // Light from parts of the environment hidden by something outside the sphere never
+    // reaches it
+    if world
+        .hit(&Ray::new(origin, direction, time), 0.0001, f32::MAX)
+        .is_some()
+    {
+        return None;
+    }
+    let power = environment.colour(&direction) * consts::PI * radius * radius / pdf;
+    Some((Ray::new(origin, -direction, time), power))
+}
+
+fn emit_from_light(lights: &[Arc<dyn Hitable>], time: f32) -> Option<(Ray, Vec3)> {
+    let sample = sample_light_surface(lights)?;
+    let emitted = sample.hit.material.emitted(&sample.hit);
+    if emitted.length_squared() <= 0.0 {
+        return None;
+    }
+    let direction = sample_emission(&sample.hit);
+    let pdf = emission_pdf(&sample.hit, &direction);
+    if pdf <= 0.0 {
+        return None;
+    }
+    let cosine = sample.hit.normal.dot(&direction.unit()).abs();
+    let power = emitted * cosine / (sample.pdf * pdf);
+    Some((Ray::new(sample.hit.point, direction, time), power))
+}
+
+// Follows a photon through mirrors and glass, storing it where it lands on any other
+// surface after at least one of them
+fn trace(
+    world: &dyn Hitable,
+    mut ray: Ray,
+    mut power: Vec3,
+    max_depth: u32,
+    photons: &mut Vec<(Vec3, Photon)>,
+) {
+    let mut bounces = 0;
+    loop {
+        let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+            Some(hit) => hit,
+            None => return,
+        };
+        // Light scattered through media is left to the path tracer
+        if hit.material.is_volumetric() {
+            return;
+        }
+        let sample = match hit.material.sample(&ray, &hit) {
+            Some(sample) if sample.specular => sample,
+            _ => {
+                if bounces > 0 {
+                    let photon = Photon {
+                        direction: ray.direction.unit(),
+                        power,
+                    };
+                    photons.push((hit.point, photon));
+                }
+                return;
+            }
+        };
+        if bounces >= max_depth {
+            return;
+        }
+        power *= sample.weight;
+        ray = Ray::new(hit.point, sample.direction, ray.time);
+        bounces += 1;
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use super::{
+    lighting::{emitted_light, environment_light, light_pdf, sample_environment, sample_lights},
+    survives_roulette, PhotonMap,
+};
+use crate::{
+    camera::Camera,
+    hitable::Hitable,
+    integrator::Integrator,
+    renderer::{RenderSettings, Splats},
+    structures::{Ray, Vec3},
+};
+
+/// Path tracing which takes caustics, the light reaching diffuse and glossy surfaces
+/// through mirrors and glass, from a photon map instead of finding them by scattering,
+/// which rarely lands on the light behind the glass. Everything else is path traced as by
+/// `PathTracer`.
+pub struct PhotonMapper {
+    // Bounces after which paths may be ended by Russian roulette
+    min_depth: u32,
+    // Bounces after which paths are always ended
+    max_depth: u32,
+    caustics: PhotonMap,
+    // Distance from each point within which photons are gathered
+    radius: f32,
+}
+
+impl PhotonMapper {
+    pub fn new(min_depth: u32, max_depth: u32, caustics: PhotonMap, radius: f32) -> Self {
+        Self {
+            min_depth,
+            max_depth,
+            caustics,
+            radius,
+        }
+    }
+
+    pub fn arc(
+        min_depth: u32,
+        max_depth: u32,
+        caustics: PhotonMap,
+        radius: f32,
+    ) -> Arc<dyn Integrator> {
+        Arc::new(Self::new(min_depth, max_depth, caustics, radius))
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hitable,
+        _camera: &Camera,
+        settings: &RenderSettings,
+        _splats: &Splats,
+    ) -> Vec3 {
+        let mut colour = Vec3::new(0.0, 0.0, 0.0);
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        let mut scatter_pdf = 0.0;
+        // Set when the last non-specular bounce was off a surface the photon map was
+        // gathered at
+        let mut gathered = false;
+        // Set while the path is passing through mirrors and glass after such a surface,
+        // where the light it finds has already been counted from the photon map
+        let mut in_caustic = false;
+        let mut depth = 0;
+        loop {
+            let hit = match world.hit(&ray, 0.0001, f32::MAX) {
+                Some(hit) => hit,
+                None => {
+                    if !in_caustic {
+                        colour += throughput * environment_light(&ray, scatter_pdf, settings);
+                    }
+                    return colour;
+                }
+            };
+            // Only the lights send out photons, so other emitters are still found this way
+            if !in_caustic || light_pdf(&ray, settings) <= 0.0 {
+                colour += throughput * emitted_light(&ray, &hit, scatter_pdf, settings);
+            }
+            if depth >= self.max_depth {
+                return colour;
+            }
+
+            colour += throughput * sample_environment(&ray, &hit, world, settings);
+            colour += throughput * sample_lights(&ray, &hit, world, settings);
+            let sample = hit.material.sample(&ray, &hit);
+            let specular = sample.as_ref().is_some_and(|sample| sample.specular);
+            // Photons are only stored on surfaces, where they land after specular bounces
+            let gathers = !specular && !hit.material.is_volumetric() && !self.caustics.is_empty();
+            if gathers {
+                colour += throughput * self.caustics.radiance(&ray, &hit, self.radius);
+            }
+            let sample = match sample {
+                Some(sample) => sample,
+                None => return colour,
+            };
+            if sample.specular {
+                in_caustic = gathered;
+            } else {
+                gathered = gathers;
+                in_caustic = false;
+            }
+            throughput *= sample.weight;
+            scatter_pdf = if sample.specular { 0.0 } else { sample.pdf };
+            ray = Ray::new(hit.point, sample.direction, ray.time);
+            depth += 1;
+
+            if depth >= self.min_depth && !survives_roulette(&mut throughput) {
+                return colour;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::PhotonMapper;
+    use crate::{
+        environments::SolidColour,
+        integrator::tests::{assert_converges, Scene},
+        integrators::{PathTracer, PhotonMap},
+        materials::{Dielectric, Diffuse, DiffuseLight, Metal},
+        random,
+        shapes::Sphere,
+        structures::Vec3,
+        Framebuffer, Hitable,
+    };
+
+    fn render_photons(scene: &Scene, photons: usize, samples: usize, seed: u64) -> Framebuffer {
+        random::reseed(seed);
+        let caustics = PhotonMap::caustics(
+            &scene.world,
+            &scene.camera,
+            scene.environment.as_ref(),
+            &scene.lights,
+            photons,
+            6,
+        );
+        assert!(!caustics.is_empty());
+        scene.render(PhotonMapper::arc(3, 6, caustics, 0.05), samples, seed)
+    }
+
+    #[test]
+    fn converges_to_the_path_tracer() {
+        // A mirror ball on a grey floor under a white sky, which reflects the sky onto the
+        // floor around it. Without photons the mapper renders only the light found by
+        // path tracing, so that it has to be added back from the map to match
+        let shapes: Vec<Arc<dyn Hitable>> = vec![
+            Sphere::arc(
+                Vec3::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Diffuse::arc(Vec3::new(0.5, 0.5, 0.5)),
+            ),
+            Sphere::arc(
+                Vec3::new(0.0, 1.0, 0.0),
+                1.0,
+                Metal::arc(Vec3::new(1.0, 1.0, 1.0), 0.0),
+            ),
+        ];
+        let mut scene = Scene::new(
+            &shapes,
+            Vec3::new(5.0, 3.0, 5.0),
+            Vec3::new(0.0, 0.3, 0.0),
+            45.0,
+            24,
+            16,
+        );
+        scene.environment = SolidColour::arc(Vec3::new(1.0, 1.0, 1.0));
+
+        let path_traced = scene.render(PathTracer::arc(3, 6), 512, 1);
+        let photon_mapped = render_photons(&scene, 200_000, 128, 2);
+        assert_converges(&path_traced, &photon_mapped, 0.05, 4, 0.05);
+    }
+
+    #[test]
+    fn focuses_light_through_glass() {
+        // A glass ball on a grey floor, lit by a small light above and to one side, which
+        // it focuses onto the floor in its shadow. The camera looks at the floor under the
+        // ball, where the path tracer only finds the caustic by chance
+        let light = Sphere::arc(
+            Vec3::new(-2.0, 6.0, 0.0),
+            1.0,
+            DiffuseLight::arc(Vec3::new(10.0, 10.0, 10.0)),
+        );
+        let shapes: Vec<Arc<dyn Hitable>> = vec![
+            Sphere::arc(
+                Vec3::new(0.0, -1000.0, 0.0),
+                1000.0,
+                Diffuse::arc(Vec3::new(0.5, 0.5, 0.5)),
+            ),
+            Sphere::arc(Vec3::new(0.0, 1.0, 0.0), 1.0, Dielectric::arc(1.5)),
+            light.clone(),
+        ];
+        let mut scene = Scene::new(
+            &shapes,
+            Vec3::new(5.0, 3.0, 2.0),
+            Vec3::new(0.5, 0.0, 0.0),
+            20.0,
+            16,
+            16,
+        );
+        scene.lights.push(light);
+
+        let path_traced = scene.render(PathTracer::arc(3, 6), 2048, 1);
+        let photon_mapped = render_photons(&scene, 1_000_000, 64, 2);
+        assert_converges(&path_traced, &photon_mapped, 0.05, 8, 0.1);
+    }
+}
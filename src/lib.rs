@@ -0,0 +1,35 @@
+//! A path tracer following Peter Shirley's "Raytracing In One x" series.
+//!
+//! Scenes are built from [`Hitable`] shapes with [`Material`]s, gathered into a [`BvhNode`],
+//! and rendered through a [`Camera`] with [`render`], which asks the chosen [`Integrator`] for
+//! the colour seen along each ray.
+
+#[macro_use]
+extern crate impl_ops;
+
+pub mod camera;
+pub mod colour;
+pub mod environment;
+pub mod environments;
+pub mod hitable;
+pub mod integrator;
+pub mod integrators;
+pub mod loaders;
+pub mod material;
+pub mod materials;
+pub mod random;
+mod renderer;
+pub mod scene;
+pub mod shapes;
+pub mod structures;
+pub mod texture;
+pub mod textures;
+
+pub use camera::Camera;
+pub use environment::Environment;
+pub use hitable::{Hitable, RayHit};
+pub use integrator::Integrator;
+pub use material::{Material, MaterialSample};
+pub use renderer::*;
+pub use structures::{BvhNode, Ray, Vec3, AABB};
+pub use texture::Texture;
@@ -0,0 +1,161 @@
+use std::{error::Error, fmt::Display, io, path::PathBuf};
+
+mod mtl;
+mod obj;
+mod voxel_grid;
+
+pub use obj::*;
+pub use voxel_grid::*;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    InvalidIndex {
+        path: PathBuf,
+        line: usize,
+        index: i64,
+    },
+    UnsupportedDirective {
+        path: PathBuf,
+        line: usize,
+        directive: String,
+    },
+    MissingMaterial {
+        path: PathBuf,
+        line: usize,
+        name: String,
+    },
+}
+
+impl ObjError {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            ObjError::Io { path, .. }
+            | ObjError::Parse { path, .. }
+            | ObjError::InvalidIndex { path, .. }
+            | ObjError::UnsupportedDirective { path, .. }
+            | ObjError::MissingMaterial { path, .. } => path,
+        }
+    }
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::InvalidIndex { path, line, index } => write!(
+                f,
+                "{}:{}: index {} does not reference an existing element",
+                path.display(),
+                line,
+                index
+            ),
+            ObjError::UnsupportedDirective {
+                path,
+                line,
+                directive,
+            } => write!(
+                f,
+                "{}:{}: unsupported directive '{}'",
+                path.display(),
+                line,
+                directive
+            ),
+            ObjError::MissingMaterial { path, line, name } => write!(
+                f,
+                "{}:{}: material '{}' was not found in any material library",
+                path.display(),
+                line,
+                name
+            ),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VoxelGridError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Header {
+        path: PathBuf,
+        message: String,
+    },
+    Size {
+        path: PathBuf,
+        expected: usize,
+        actual: usize,
+    },
+    Value {
+        path: PathBuf,
+        voxel: [usize; 3],
+        message: String,
+    },
+}
+
+impl Display for VoxelGridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoxelGridError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            VoxelGridError::Header { path, message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+            VoxelGridError::Size {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: expected {} bytes for the grid's dimensions, found {}",
+                path.display(),
+                expected,
+                actual
+            ),
+            VoxelGridError::Value {
+                path,
+                voxel: [x, y, z],
+                message,
+            } => write!(
+                f,
+                "{}: voxel ({}, {}, {}): {}",
+                path.display(),
+                x,
+                y,
+                z,
+                message
+            ),
+        }
+    }
+}
+
+impl Error for VoxelGridError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            VoxelGridError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
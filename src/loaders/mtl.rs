@@ -0,0 +1,171 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::{
+    material::Material,
+    materials::{Dielectric, Diffuse, Metal},
+    structures::Vec3,
+};
+
+use super::ObjError;
+
+// Statements which are valid MTL but have no equivalent in our materials.
+// Texture maps (map_*) are skipped as well.
+const IGNORED_DIRECTIVES: [&str; 12] = [
+    "Ka",
+    "Ke",
+    "Tf",
+    "sharpness",
+    "Pr",
+    "Pm",
+    "Ps",
+    "Pc",
+    "Pcr",
+    "aniso",
+    "anisor",
+    "bump",
+];
+
+const DEFAULT_REFRACTIVE_INDEX: f32 = 1.5;
+
+struct MtlDescription {
+    diffuse: Vec3,
+    specular: Vec3,
+    shininess: f32,
+    dissolve: f32,
+    refractive_index: Option<f32>,
+    illum: u32,
+}
+
+impl MtlDescription {
+    fn new() -> Self {
+        Self {
+            diffuse: Vec3::new(0.8, 0.8, 0.8),
+            specular: Vec3::new(0.0, 0.0, 0.0),
+            shininess: 0.0,
+            dissolve: 1.0,
+            refractive_index: None,
+            illum: 2,
+        }
+    }
+
+    fn into_material(self) -> Arc<dyn Material> {
+        let is_transparent = self.dissolve < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9);
+        let is_reflective = self.illum == 3
+            || (self.illum != 1 && luminance(&self.specular) > luminance(&self.diffuse));
+        if is_transparent {
+            Dielectric::arc(self.refractive_index.unwrap_or(DEFAULT_REFRACTIVE_INDEX))
+        } else if is_reflective {
+            // Map the Phong exponent onto fuzz the same way it maps onto a microfacet roughness
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            Metal::arc(self.specular, fuzz)
+        } else {
+            Diffuse::arc(self.diffuse)
+        }
+    }
+}
+
+fn luminance(colour: &Vec3) -> f32 {
+    0.2126 * colour.r() + 0.7152 * colour.g() + 0.0722 * colour.b()
+}
+
+pub(super) fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlDescription)> = None;
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let parse_error = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let directive = match tokens.next() {
+            Some(directive) => directive,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if directive == "newmtl" {
+            if arguments.is_empty() {
+                return Err(parse_error("newmtl requires a material name".to_string()));
+            }
+            if let Some((name, description)) = current.take() {
+                materials.insert(name, description.into_material());
+            }
+            current = Some((arguments.join(" "), MtlDescription::new()));
+            continue;
+        }
+
+        if IGNORED_DIRECTIVES.contains(&directive) || directive.starts_with("map_") {
+            continue;
+        }
+
+        let description = match current.as_mut() {
+            Some((_, description)) => description,
+            None => {
+                return Err(parse_error(format!(
+                    "{} appears before any newmtl statement",
+                    directive
+                )))
+            }
+        };
+        match directive {
+            "Kd" => description.diffuse = parse_colour(&arguments).map_err(parse_error)?,
+            "Ks" => description.specular = parse_colour(&arguments).map_err(parse_error)?,
+            "Ns" => description.shininess = parse_scalar(&arguments).map_err(parse_error)?,
+            "d" => description.dissolve = parse_scalar(&arguments).map_err(parse_error)?,
+            "Tr" => {
+                description.dissolve = 1.0 - parse_scalar::<f32>(&arguments).map_err(parse_error)?
+            }
+            "Ni" => {
+                description.refractive_index = Some(parse_scalar(&arguments).map_err(parse_error)?)
+            }
+            "illum" => {
+                description.illum = parse_scalar::<u32>(&arguments).map_err(parse_error)?;
+            }
+            _ => {
+                return Err(ObjError::UnsupportedDirective {
+                    path: path.to_path_buf(),
+                    line: line_number,
+                    directive: directive.to_string(),
+                })
+            }
+        }
+    }
+    if let Some((name, description)) = current.take() {
+        materials.insert(name, description.into_material());
+    }
+    Ok(materials)
+}
+
+fn parse_scalar<T: std::str::FromStr>(arguments: &[&str]) -> Result<T, String> {
+    match arguments {
+        [value] => value
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid number", value)),
+        _ => Err(format!("expected 1 value, found {}", arguments.len())),
+    }
+}
+
+fn parse_colour(arguments: &[&str]) -> Result<Vec3, String> {
+    let values = arguments
+        .iter()
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("'{}' is not a valid number", value))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    match values[..] {
+        [grey] => Ok(Vec3::new(grey, grey, grey)),
+        [r, g, b] => Ok(Vec3::new(r, g, b)),
+        _ => Err(format!("expected 1 or 3 values, found {}", values.len())),
+    }
+}
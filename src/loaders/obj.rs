@@ -0,0 +1,404 @@
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+use crate::{
+    hitable::Hitable, material::Material, materials::Diffuse, shapes::TriangleMesh,
+    structures::Vec3,
+};
+
+use super::{mtl::load_mtl, ObjError};
+
+// Statements which are valid OBJ but do not change the triangles we produce.
+const IGNORED_DIRECTIVES: [&str; 4] = ["o", "g", "s", "mg"];
+
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+#[derive(Default)]
+struct MeshBuilder {
+    vertex_lookup: HashMap<VertexKey, u32>,
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+    has_normals: bool,
+    has_uvs: bool,
+    indices: Vec<[u32; 3]>,
+}
+
+impl MeshBuilder {
+    fn new() -> Self {
+        Self {
+            has_normals: true,
+            has_uvs: true,
+            ..Default::default()
+        }
+    }
+
+    fn vertex(&mut self, key: VertexKey, data: &ObjData, face_normal: Vec3) -> u32 {
+        let (position, uv, normal) = key;
+        // Some exporters write `vn 0 0 0`, which has no direction to shade with, so
+        // vertices using it take the normal of each face they are part of instead
+        let normal = normal.map(|normal| data.normals[normal]);
+        let shared = normal.is_none_or(|normal| normal.length_squared() > 0.0);
+        if let Some(&index) = self.vertex_lookup.get(&key).filter(|_| shared) {
+            return index;
+        }
+        let index = self.positions.len() as u32;
+        self.positions.push(data.positions[position]);
+        match uv {
+            Some(uv) => self.uvs.push(data.uvs[uv]),
+            None => {
+                self.has_uvs = false;
+                self.uvs.push((0.0, 0.0));
+            }
+        }
+        match normal {
+            Some(normal) if shared => self.normals.push(normal),
+            Some(_) => self.normals.push(face_normal),
+            None => {
+                self.has_normals = false;
+                self.normals.push(Vec3::new(0.0, 0.0, 0.0));
+            }
+        }
+        if shared {
+            self.vertex_lookup.insert(key, index);
+        }
+        index
+    }
+
+    fn build(self, material: Arc<dyn Material>) -> Arc<dyn Hitable> {
+        TriangleMesh::arc(
+            self.positions,
+            Some(self.normals).filter(|_| self.has_normals),
+            Some(self.uvs).filter(|_| self.has_uvs),
+            self.indices,
+            material,
+        )
+    }
+}
+
+#[derive(Default)]
+struct ObjData {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f32, f32)>,
+}
+
+/// Loads a Wavefront OBJ file, producing one mesh for each material it uses.
+/// Faces which appear before any `usemtl` statement are given a grey diffuse material.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Vec<Arc<dyn Hitable>>, ObjError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let default_material = Diffuse::arc(Vec3::new(0.5, 0.5, 0.5));
+    let mut library: HashMap<String, Arc<dyn Material>> = HashMap::new();
+    let mut data = ObjData::default();
+    // Meshes are kept in the order their material is first used, so output is stable.
+    let mut meshes: Vec<(Arc<dyn Material>, MeshBuilder)> =
+        vec![(default_material, MeshBuilder::new())];
+    let mut mesh_lookup: HashMap<String, usize> = HashMap::new();
+    let mut current_mesh = 0;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let parse_error = |message: String| ObjError::Parse {
+            path: path.to_path_buf(),
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let directive = match tokens.next() {
+            Some(directive) => directive,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match directive {
+            "v" => {
+                let values = parse_floats(&arguments, 3, 4).map_err(parse_error)?;
+                data.positions
+                    .push(Vec3::new(values[0], values[1], values[2]));
+            }
+            "vn" => {
+                let values = parse_floats(&arguments, 3, 3).map_err(parse_error)?;
+                let normal = Vec3::new(values[0], values[1], values[2]);
+                data.normals.push(if normal.length_squared() > 0.0 {
+                    normal.unit()
+                } else {
+                    normal
+                });
+            }
+            "vt" => {
+                let values = parse_floats(&arguments, 1, 3).map_err(parse_error)?;
+                data.uvs
+                    .push((values[0], values.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parse_error(format!(
+                        "a face needs at least 3 vertices, found {}",
+                        arguments.len()
+                    )));
+                }
+                let keys = arguments
+                    .iter()
+                    .map(|argument| parse_face_vertex(argument, &data, path, line_number))
+                    .collect::<Result<Vec<VertexKey>, ObjError>>()?;
+                let face_normal = face_normal(&keys, &data);
+                let builder = &mut meshes[current_mesh].1;
+                let indices: Vec<u32> = keys
+                    .iter()
+                    .map(|&key| builder.vertex(key, &data, face_normal))
+                    .collect();
+                // Polygons are assumed to be convex, and triangulated as a fan
+                for i in 1..indices.len() - 1 {
+                    builder
+                        .indices
+                        .push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(parse_error("mtllib requires a file name".to_string()));
+                }
+                for file_name in arguments {
+                    library.extend(load_mtl(&directory.join(file_name))?);
+                }
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                current_mesh = match mesh_lookup.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let material = library.get(&name).cloned().ok_or_else(|| {
+                            ObjError::MissingMaterial {
+                                path: path.to_path_buf(),
+                                line: line_number,
+                                name: name.clone(),
+                            }
+                        })?;
+                        meshes.push((material, MeshBuilder::new()));
+                        mesh_lookup.insert(name, meshes.len() - 1);
+                        meshes.len() - 1
+                    }
+                };
+            }
+            _ if IGNORED_DIRECTIVES.contains(&directive) => {}
+            _ => {
+                return Err(ObjError::UnsupportedDirective {
+                    path: path.to_path_buf(),
+                    line: line_number,
+                    directive: directive.to_string(),
+                })
+            }
+        }
+    }
+
+    Ok(meshes
+        .into_iter()
+        .filter(|(_, builder)| !builder.indices.is_empty())
+        .map(|(material, builder)| builder.build(material))
+        .collect())
+}
+
+// The normal of the whole polygon, from the sum of the areas of its fan of triangles,
+// so that it is found even where the first three vertices are in a line. Zero where the
+// polygon has no area at all.
+fn face_normal(keys: &[VertexKey], data: &ObjData) -> Vec3 {
+    let first = data.positions[keys[0].0];
+    let area = keys
+        .windows(2)
+        .skip(1)
+        .fold(Vec3::new(0.0, 0.0, 0.0), |area, pair| {
+            let [a, b] = [pair[0], pair[1]].map(|key| data.positions[key.0] - first);
+            area + a.cross(&b)
+        });
+    if area.length_squared() > 0.0 {
+        area.unit()
+    } else {
+        area
+    }
+}
+
+fn parse_floats(arguments: &[&str], min: usize, max: usize) -> Result<Vec<f32>, String> {
+    if arguments.len() < min || arguments.len() > max {
+        return Err(format!(
+            "expected between {} and {} values, found {}",
+            min,
+            max,
+            arguments.len()
+        ));
+    }
+    arguments
+        .iter()
+        .map(|value| {
+            value
+                .parse::<f32>()
+                .map_err(|_| format!("'{}' is not a valid number", value))
+        })
+        .collect()
+}
+
+fn parse_face_vertex(
+    argument: &str,
+    data: &ObjData,
+    path: &Path,
+    line: usize,
+) -> Result<VertexKey, ObjError> {
+    let mut parts = argument.split('/');
+    let position = parts.next().unwrap_or("");
+    let uv = parts.next().filter(|part| !part.is_empty());
+    let normal = parts.next().filter(|part| !part.is_empty());
+    if parts.next().is_some() {
+        return Err(ObjError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message: format!("'{}' is not a valid face vertex", argument),
+        });
+    }
+
+    let resolve = |value: &str, count: usize| -> Result<usize, ObjError> {
+        let index = value.parse::<i64>().map_err(|_| ObjError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message: format!("'{}' is not a valid index", value),
+        })?;
+        // OBJ indices are 1-based, and negative indices count back from the latest element
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            Err(ObjError::InvalidIndex {
+                path: path.to_path_buf(),
+                line,
+                index,
+            })
+        } else {
+            Ok(resolved as usize)
+        }
+    };
+
+    Ok((
+        resolve(position, data.positions.len())?,
+        uv.map(|uv| resolve(uv, data.uvs.len())).transpose()?,
+        normal
+            .map(|normal| resolve(normal, data.normals.len()))
+            .transpose()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, sync::Arc};
+
+    use super::load_obj;
+    use crate::{
+        hitable::Hitable,
+        loaders::ObjError,
+        structures::{Ray, Vec3},
+    };
+
+    // Writes `source` to a temporary file named after the test and loads it
+    fn load(name: &str, source: &str) -> Result<Vec<Arc<dyn Hitable>>, ObjError> {
+        let path = temporary_path(name);
+        fs::write(&path, source).unwrap();
+        let meshes = load_obj(&path);
+        fs::remove_file(&path).unwrap();
+        meshes
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.obj", name, std::process::id()))
+    }
+
+    // Checks the normal of the first mesh where a ray down the z axis at `x` and `y` hits
+    fn assert_normal(meshes: &[Arc<dyn Hitable>], x: f32, y: f32, expected: Vec3) {
+        let ray = Ray::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = meshes[0].hit(&ray, 0.0, f32::MAX).unwrap();
+        assert!(
+            (hit.normal - expected).length() < 1e-4,
+            "normal at ({}, {}) is ({}, {}, {})",
+            x,
+            y,
+            hit.normal.x(),
+            hit.normal.y(),
+            hit.normal.z()
+        );
+    }
+
+    #[test]
+    fn zero_normals_fall_back_to_the_face_normal() {
+        let meshes = load(
+            "zero_normals",
+            "v 0 0 -1\nv 1 0 -1\nv 0 1 -1\nv 1 1 -1\nvn 0 0 0\nvn 0 0 1\n\
+             f 1//1 2//1 3//2\nf 2//1 4//1 3//1\n",
+        )
+        .unwrap();
+        for (x, y) in [(0.2, 0.2), (0.8, 0.8)] {
+            assert_normal(&meshes, x, y, Vec3::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn face_normals_cover_the_whole_polygon() {
+        // The first three vertices are in a line, so give no normal by themselves
+        let meshes = load(
+            "collinear_face",
+            "v 0 0 -1\nv 0.5 0 -1\nv 1 0 -1\nv 0 1 -1\nvn 0 0 0\nf 1//1 2//1 3//1 4//1\n",
+        )
+        .unwrap();
+        assert_normal(&meshes, 0.2, 0.2, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let path = temporary_path("missing");
+        assert!(matches!(
+            load_obj(&path),
+            Err(ObjError::Io { path: error_path, .. }) if error_path == path
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_indices() {
+        let vertices = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        for (face, expected) in [("f 1 2 4", 4), ("f 0 1 2", 0), ("f 1 2 -4", -4)] {
+            let source = format!("{}\n{}\n", vertices, face);
+            match load("invalid_index", &source) {
+                Err(ObjError::InvalidIndex { line: 5, index, .. }) => assert_eq!(index, expected),
+                Err(error) => panic!("'{}' gave the wrong error: {}", face, error),
+                Ok(_) => panic!("'{}' was accepted", face),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_faces_with_too_few_vertices() {
+        assert!(matches!(
+            load("short_face", "v 0 0 0\nv 1 0 0\nf 1 2\n"),
+            Err(ObjError::Parse { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_unsupported_directives() {
+        assert!(matches!(
+            load("unsupported", "v 0 0 0\ncurv 0 1 1 2\n"),
+            Err(ObjError::UnsupportedDirective { line: 2, directive, .. }) if directive == "curv"
+        ));
+    }
+
+    #[test]
+    fn rejects_missing_materials() {
+        assert!(matches!(
+            load("missing_material", "v 0 0 0\nusemtl chrome\n"),
+            Err(ObjError::MissingMaterial { line: 2, name, .. }) if name == "chrome"
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fs, path::Path};
+
+use crate::structures::VoxelGrid;
+
+use super::VoxelGridError;
+
+const MAGIC: &[u8; 4] = b"VGRD";
+// The magic, then the three dimensions and the channel count as u32s
+const HEADER_SIZE: usize = 20;
+
+/// Loads a dense voxel grid. The file starts with the ASCII magic `VGRD` and four
+/// little endian u32s: the dimensions along x, y and z, and the number of channels,
+/// which is 1 for density alone or 2 for density and temperature. Little endian f32
+/// values follow, with the channels of each voxel together and voxels ordered with x
+/// varying fastest, then y, then z.
+pub fn load_voxel_grid(path: impl AsRef<Path>) -> Result<VoxelGrid, VoxelGridError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| VoxelGridError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_voxel_grid(path, &bytes)
+}
+
+// Errors name `path`, which the bytes were read from
+fn parse_voxel_grid(path: &Path, bytes: &[u8]) -> Result<VoxelGrid, VoxelGridError> {
+    let header_error = |message: &str| VoxelGridError::Header {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    };
+
+    if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+        return Err(header_error("not a voxel grid, expected the magic 'VGRD'"));
+    }
+    let word = |index: usize| {
+        let start = 4 + index * 4;
+        u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap()) as usize
+    };
+    let dimensions = [word(0), word(1), word(2)];
+    let channels = word(3);
+    if dimensions.contains(&0) {
+        return Err(header_error("dimensions must be greater than zero"));
+    }
+    if channels != 1 && channels != 2 {
+        return Err(header_error(&format!(
+            "expected 1 or 2 channels, found {}",
+            channels
+        )));
+    }
+    let expected_size = dimensions
+        .iter()
+        .try_fold(channels * 4, |size, &dimension| size.checked_mul(dimension))
+        .and_then(|size| size.checked_add(HEADER_SIZE))
+        .ok_or_else(|| header_error("dimensions are too large"))?;
+    if bytes.len() != expected_size {
+        return Err(VoxelGridError::Size {
+            path: path.to_path_buf(),
+            expected: expected_size,
+            actual: bytes.len(),
+        });
+    }
+
+    let voxel_count: usize = dimensions.iter().product();
+    let mut density = Vec::with_capacity(voxel_count);
+    let mut temperature = Vec::with_capacity(if channels == 2 { voxel_count } else { 0 });
+    for (index, voxel) in bytes[HEADER_SIZE..].chunks_exact(channels * 4).enumerate() {
+        let value = |channel: usize| {
+            let start = channel * 4;
+            f32::from_le_bytes(voxel[start..start + 4].try_into().unwrap())
+        };
+        let value_error = |message: &str| VoxelGridError::Value {
+            path: path.to_path_buf(),
+            voxel: [
+                index % dimensions[0],
+                index / dimensions[0] % dimensions[1],
+                index / (dimensions[0] * dimensions[1]),
+            ],
+            message: message.to_string(),
+        };
+        let voxel_density = value(0);
+        if !(voxel_density.is_finite() && voxel_density >= 0.0) {
+            return Err(value_error("density must be finite and not negative"));
+        }
+        density.push(voxel_density);
+        if channels == 2 {
+            let voxel_temperature = value(1);
+            if !(voxel_temperature.is_finite() && voxel_temperature >= 0.0) {
+                return Err(value_error("temperature must be finite and not negative"));
+            }
+            temperature.push(voxel_temperature);
+        }
+    }
+    Ok(VoxelGrid::new(
+        dimensions,
+        density,
+        Some(temperature).filter(|_| channels == 2),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{load_voxel_grid, parse_voxel_grid, HEADER_SIZE, MAGIC};
+    use crate::loaders::VoxelGridError;
+
+    fn grid_bytes(dimensions: [u32; 3], channels: u32, values: &[f32]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for word in dimensions.iter().chain([&channels]) {
+            bytes.extend(word.to_le_bytes());
+        }
+        for value in values {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn parse(bytes: &[u8]) -> Result<(), VoxelGridError> {
+        parse_voxel_grid(Path::new("test.vgrid"), bytes).map(|_| ())
+    }
+
+    fn header_message(result: Result<(), VoxelGridError>) -> String {
+        match result {
+            Err(VoxelGridError::Header { message, .. }) => message,
+            Err(error) => panic!("expected a header error, got {}", error),
+            Ok(()) => panic!("expected a header error"),
+        }
+    }
+
+    #[test]
+    fn loads_density_and_temperature() {
+        let grid = parse_voxel_grid(
+            Path::new("test.vgrid"),
+            &grid_bytes([2, 1, 1], 2, &[0.5, 1000.0, 2.0, 1500.0]),
+        )
+        .unwrap();
+        assert_eq!(grid.dimensions(), [2, 1, 1]);
+        assert!(grid.has_temperature());
+        assert_eq!(grid.max_density(), 2.0);
+        assert_eq!(grid.max_temperature(), 1500.0);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let mut bad_magic = grid_bytes([1, 1, 1], 1, &[1.0]);
+        bad_magic[0] = b'X';
+        assert!(header_message(parse(&bad_magic)).contains("magic"));
+        assert!(header_message(parse(&MAGIC[..])).contains("magic"));
+        assert!(header_message(parse(&grid_bytes([1, 0, 1], 1, &[]))).contains("dimensions"));
+        assert!(header_message(parse(&grid_bytes([1, 1, 1], 3, &[1.0; 3]))).contains("channels"));
+        let huge = u32::MAX;
+        assert!(header_message(parse(&grid_bytes([huge, huge, huge], 2, &[]))).contains("large"));
+    }
+
+    #[test]
+    fn rejects_the_wrong_size() {
+        match parse(&grid_bytes([2, 2, 1], 1, &[1.0; 3])) {
+            Err(VoxelGridError::Size {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected, HEADER_SIZE + 16);
+                assert_eq!(actual, HEADER_SIZE + 12);
+            }
+            _ => panic!("expected a size error"),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        for (values, bad) in [
+            ([0.0, 0.0, f32::NAN, 0.0], "density"),
+            ([0.0, 0.0, -1.0, 0.0], "density"),
+            ([0.0, 0.0, 1.0, f32::INFINITY], "temperature"),
+        ] {
+            match parse(&grid_bytes([2, 1, 1], 2, &values)) {
+                Err(VoxelGridError::Value { voxel, message, .. }) => {
+                    assert_eq!(voxel, [1, 0, 0]);
+                    assert!(message.contains(bad), "{}", message);
+                }
+                _ => panic!("expected a value error for the {}", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        match load_voxel_grid("does/not/exist.vgrid") {
+            Err(VoxelGridError::Io { path, .. }) => {
+                assert_eq!(path, Path::new("does/not/exist.vgrid"))
+            }
+            _ => panic!("expected an IO error"),
+        }
+    }
+}
@@ -1,179 +1,215 @@
-use std::{
-    mem::size_of,
-    sync::{Arc, Mutex},
-    time::SystemTime,
+use std::{io::Write, sync::Arc, time::SystemTime};
+
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use cli::{Args, IntegratorKind};
+use rand::Rng;
+use raytracing_in_a_weekend::{
+    integrators::{
+        AmbientOcclusion, BidirectionalPathTracer, BvhCost, Depth, DirectLighting, Metropolis,
+        Normals, PathTracer, PhotonMap, PhotonMapper,
+    },
+    random, render,
+    scene::{load_scene_with_progress, random_scene, ImageOverrides, Scene},
+    structures::BvhBuildProgress,
+    Framebuffer, Hitable, Integrator, RenderSettings, Vec3, AABB,
 };
 
-use camera::Camera;
-use hitable::Hitable;
-use image::png::PngEncoder;
-use materials::{Diffuse, Metal};
-use rand::{thread_rng, Rng};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use structures::{BvhNode, Ray, Vec3};
+mod cli;
 
-#[macro_use]
-extern crate impl_ops;
+fn main() {
+    let args = Args::parse();
+    if args.rounds > 1 && !matches!(args.integrator, IntegratorKind::Metropolis) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--rounds can only be used with --integrator metropolis",
+            )
+            .exit();
+    }
 
-use crate::{materials::Dielectric, shapes::Sphere};
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build_global()
+            .unwrap();
+    }
 
-mod camera;
-mod hitable;
-mod material;
-mod materials;
-mod shapes;
-mod structures;
+    let seed = args.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    if !args.quiet {
+        println!("Using seed {}", seed);
+    }
+    random::reseed(seed);
 
-fn random_scene() -> BvhNode {
-    let n = 500;
-    let mut rng = thread_rng();
-    let mut list = Vec::<Arc<dyn Hitable>>::with_capacity(n + 1);
-    list.push(Sphere::arc(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        Diffuse::arc(Vec3::new(0.5, 0.5, 0.5)),
-    ));
-    let headliners_plane = Vec3::new(4.0, 0.2, 0.0);
-    for a in -11..11 {
-        for b in -11..11 {
-            let mat_choice = rng.gen::<f32>();
-            let centre = Vec3::new(
-                a as f32 + 0.9 + rng.gen::<f32>(),
-                0.2,
-                b as f32 + 0.9 + rng.gen::<f32>(),
-            );
-            if (centre - headliners_plane).length() > 0.9 {
-                let mat = if mat_choice < 0.8 {
-                    Diffuse::arc(Vec3::new(
-                        rng.gen::<f32>() * rng.gen::<f32>(),
-                        rng.gen::<f32>() * rng.gen::<f32>(),
-                        rng.gen::<f32>() * rng.gen::<f32>(),
-                    ))
-                } else if mat_choice < 0.95 {
-                    Metal::arc(
-                        Vec3::new(
-                            rng.gen::<f32>() * rng.gen::<f32>(),
-                            rng.gen::<f32>() * rng.gen::<f32>(),
-                            rng.gen::<f32>() * rng.gen::<f32>(),
-                        ),
-                        0.5 * rng.gen::<f32>(),
-                    )
-                } else {
-                    Dielectric::arc(1.5)
-                };
-                list.push(Sphere::arc(centre, 0.2, mat));
+    let overrides = ImageOverrides {
+        width: args.width.map(|width| width as usize),
+        height: args.height.map(|height| height as usize),
+        samples: args.samples.map(|samples| samples as usize),
+    };
+    let scene = match &args.scene {
+        Some(scene_path) => match load_scene_with_progress(scene_path, &overrides, |progress| {
+            if !args.quiet {
+                print_build_progress(progress)
             }
-        }
+        }) {
+            Ok(scene) => scene,
+            Err(error) => {
+                eprintln!("Failed to load scene: {}", error);
+                std::process::exit(1);
+            }
+        },
+        None => random_scene(
+            overrides.width.unwrap_or(3840),
+            overrides.height.unwrap_or(2160),
+            overrides.samples.unwrap_or(100),
+        ),
+    };
+    if !args.quiet {
+        println!("BVH: {}", scene.world.stats());
     }
+    // Kept apart from the other integrators so that it can also render in rounds
+    let metropolis = matches!(args.integrator, IntegratorKind::Metropolis).then(|| {
+        Arc::new(Metropolis::new(
+            args.min_depth,
+            args.max_depth,
+            args.bootstrap_samples as usize,
+            args.chains as usize,
+        ))
+    });
+    let integrator: Arc<dyn Integrator> = match &metropolis {
+        Some(metropolis) => metropolis.clone(),
+        None => integrator(&args, &scene),
+    };
+    let settings = RenderSettings {
+        width: scene.width,
+        height: scene.height,
+        samples: scene.samples,
+        seed,
+        environment: scene.environment,
+        lights: scene.lights,
+        integrator,
+    };
 
-    list.push(Sphere::arc(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        Dielectric::arc(1.5),
-    ));
-
-    list.push(Sphere::arc(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Diffuse::arc(Vec3::new(0.8, 0.8, 0.8)),
-    ));
-
-    list.push(Sphere::arc(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        Metal::arc(Vec3::new(0.7, 0.6, 0.5), 0.0),
-    ));
+    let now = SystemTime::now();
+    if !args.quiet {
+        println!("Starting render");
+    }
+    let framebuffer = match &metropolis {
+        Some(metropolis) if args.rounds > 1 => {
+            let mut round = 0;
+            metropolis.render_progressively(
+                &scene.world,
+                &scene.camera,
+                &settings,
+                args.rounds as usize,
+                |framebuffer| {
+                    round += 1;
+                    save_png(framebuffer, &args);
+                    if !args.quiet {
+                        println!(
+                            "Round {} of {} written after {} milliseconds",
+                            round,
+                            args.rounds,
+                            now.elapsed().unwrap().as_millis()
+                        );
+                    }
+                },
+            )
+        }
+        _ => render(&scene.world, &scene.camera, &settings),
+    };
+    let elapsed_millis = now.elapsed().unwrap().as_millis();
+    if !args.quiet {
+        if elapsed_millis > 1000 {
+            println!(
+                "Image rendered in {} seconds",
+                elapsed_millis as f32 / 1000.0
+            );
+        } else {
+            println!("Image rendered in {} milliseconds", elapsed_millis);
+        }
+    }
+    save_png(&framebuffer, &args);
+}
 
-    BvhNode::new(&list[..], 0.0, 0.0)
+fn save_png(framebuffer: &Framebuffer, args: &Args) {
+    if let Err(error) = framebuffer.save_png(&args.output) {
+        eprintln!("Failed to write {}: {}", args.output.display(), error);
+        std::process::exit(1);
+    }
 }
 
-fn ray_colour(ray: &Ray, hitable: &dyn Hitable, depth: i32) -> Vec3 {
-    if let Some(hit) = hitable.hit(ray, 0.0001, f32::MAX) {
-        if depth < 50 {
-            if let Some(mat_hit) = hit.material.scatter(ray, &hit) {
-                return mat_hit.attenuation * ray_colour(&mat_hit.scatter_ray, hitable, depth + 1);
+fn integrator(args: &Args, scene: &Scene) -> Arc<dyn Integrator> {
+    let camera = &scene.camera;
+    // Debug views default to distances in proportion to the scene, whatever its units
+    let bounding_box = scene
+        .world
+        .bounding_box(0.0, 1.0)
+        .unwrap_or_else(|| AABB::new(camera.origin(), camera.origin()));
+    let scene_size = Some((bounding_box.max - bounding_box.min).length())
+        .filter(|size| size.is_finite() && *size > 0.0)
+        .unwrap_or(1.0);
+    let near = bounding_box.min - camera.origin();
+    let far = bounding_box.max - camera.origin();
+    let furthest_corner = Vec3::new(
+        near.x().abs().max(far.x().abs()),
+        near.y().abs().max(far.y().abs()),
+        near.z().abs().max(far.z().abs()),
+    );
+    let view_depth = Some(furthest_corner.length())
+        .filter(|depth| depth.is_finite() && *depth > 0.0)
+        .unwrap_or(scene_size);
+    match args.integrator {
+        IntegratorKind::Path => PathTracer::arc(args.min_depth, args.max_depth),
+        IntegratorKind::Bidirectional => {
+            BidirectionalPathTracer::arc(args.min_depth, args.max_depth)
+        }
+        IntegratorKind::Photon => {
+            let now = SystemTime::now();
+            let caustics = PhotonMap::caustics(
+                &scene.world,
+                camera,
+                scene.environment.as_ref(),
+                &scene.lights,
+                args.photons as usize,
+                args.max_depth,
+            );
+            if !args.quiet {
+                println!(
+                    "Photon map: {} caustic photons stored in {} milliseconds",
+                    caustics.len(),
+                    now.elapsed().unwrap().as_millis()
+                );
             }
+            let radius = args.photon_radius.unwrap_or_else(|| {
+                Some(caustics.caster_radius() / 50.0)
+                    .filter(|radius| *radius > 0.0)
+                    .unwrap_or(0.01 * scene_size)
+            });
+            PhotonMapper::arc(args.min_depth, args.max_depth, caustics, radius)
         }
-        Vec3::new(0.0, 0.0, 0.0)
-    } else {
-        let unit_direction = ray.direction.unit();
-        let t = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - t) * Vec3::new(1.0, 1.0, 1.0) + t * Vec3::new(0.5, 0.7, 1.0)
+        IntegratorKind::Metropolis => unreachable!("Metropolis is built by main"),
+        IntegratorKind::Direct => DirectLighting::arc(args.max_depth),
+        IntegratorKind::AmbientOcclusion => {
+            AmbientOcclusion::arc(args.ao_distance.unwrap_or(0.1 * scene_size))
+        }
+        IntegratorKind::Normals => Normals::arc(),
+        IntegratorKind::Depth => Depth::arc(args.max_distance.unwrap_or(view_depth)),
+        IntegratorKind::BvhCost => BvhCost::arc(args.max_cost as usize),
     }
 }
 
-fn main() {
-    let nx = 3840;
-    let ny = 2160;
-    let num_pixels = nx * ny;
-    let pixel_size = size_of::<u16>() * 3;
-    let samples = 100;
-
-    let world = random_scene();
-    let cam_pos = Vec3::new(13.0, 2.0, 3.0);
-    let cam_target = Vec3::new(0.0, 0.0, 0.0);
-    let cam_focus_dist = 10.0;
-    let camera = Camera::new(
-        cam_pos,
-        cam_target,
-        Vec3::new(0.0, 1.0, 0.0),
-        20.0,
-        nx as f32 / ny as f32,
-        0.0,
-        cam_focus_dist,
-        0.0,
-        0.0,
+fn print_build_progress(progress: &BvhBuildProgress) {
+    let line = format!(
+        "Building BVH: level {}, {} nodes, {:.1}% of primitives placed",
+        progress.level,
+        progress.level_node_count,
+        100.0 * progress.primitives_in_leaves as f32 / progress.primitive_count as f32
     );
-    let image_bytes = Arc::new(Mutex::new(vec![0; num_pixels as usize * pixel_size]));
+    // Padded to overwrite the whole of a longer previous line
+    print!("\r{:<72}", line);
 
-    let now = SystemTime::now();
-    println!("Starting render");
-    (0..num_pixels).into_par_iter().for_each(|idx| {
-        let mut rng = thread_rng();
-        let j = ny - idx / nx;
-        let i = idx % nx;
-        let mut col = Vec3::new(0.0, 0.0, 0.0);
-        for _ in 0..samples {
-            let u_jitter: f32 = rng.gen();
-            let v_jitter: f32 = rng.gen();
-            let u = (i as f32 + u_jitter) / nx as f32;
-            let v = (j as f32 + v_jitter) / ny as f32;
-            let ray = camera.get_ray(u, v);
-            col += ray_colour(&ray, &world, 0);
-        }
-        col /= samples as f32;
-        col = Vec3::new(col[0].sqrt(), col[1].sqrt(), col[2].sqrt());
-        let hdr_rgb = [
-            (65534.99 * col.r()) as u16,
-            (65534.99 * col.g()) as u16,
-            (65534.99 * col.b()) as u16,
-        ];
-        let mut ctr = 0;
-        hdr_rgb
-            .iter()
-            .flat_map(|channel| channel.to_be_bytes())
-            .for_each(|byte| {
-                image_bytes.lock().unwrap()[idx * pixel_size + ctr] = byte;
-                ctr += 1
-            });
-    });
-    let elapsed_millis = now.elapsed().unwrap().as_millis();
-    if elapsed_millis > 1000 {
-        println!(
-            "Image rendered in {} seconds",
-            elapsed_millis as f32 / 1000.0
-        );
-    } else {
-        println!("Image rendered in {} milliseconds", elapsed_millis);
+    if progress.primitives_in_leaves == progress.primitive_count {
+        println!();
     }
-    let mut file = std::fs::File::create("raytracing.png").unwrap();
-    let png_encoder = PngEncoder::new(&mut file);
-    png_encoder
-        .encode(
-            &image_bytes.lock().unwrap(),
-            nx as u32,
-            ny as u32,
-            image::ColorType::Rgb16,
-        )
-        .unwrap();
+    std::io::stdout().flush().unwrap();
 }
@@ -3,10 +3,134 @@ use crate::{
     structures::{Ray, Vec3},
 };
 
-pub struct MaterialHit {
-    pub attenuation: Vec3,
-    pub scatter_ray: Ray,
+/// A direction chosen by [`Material::sample`], with the factor by which light arriving
+/// from it is multiplied on its way back along the incoming ray.
+pub struct MaterialSample {
+    pub direction: Vec3,
+    /// `eval / pdf` for the chosen direction, or the attenuation of a specular lobe.
+    pub weight: Vec3,
+    /// Probability density, per unit solid angle, of choosing the direction. Zero for
+    /// specular lobes.
+    pub pdf: f32,
+    /// Specular lobes, such as mirrors and glass, scatter into a single direction, so
+    /// can't be evaluated for any other and are never sampled towards lights.
+    pub specular: bool,
 }
+
 pub trait Material: Sync + Send {
-    fn scatter(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialHit>;
+    /// Chooses a direction to continue a path in, or None if the light is absorbed.
+    fn sample(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialSample>;
+
+    /// Fraction of light arriving from `direction` which leaves back along the incoming
+    /// ray, per unit solid angle, including the cosine term. Zero for specular materials.
+    fn eval(&self, _in_ray: &Ray, _hit: &RayHit, _direction: &Vec3) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Probability density, per unit solid angle, of `sample` choosing `direction`.
+    /// Zero for specular materials.
+    fn pdf(&self, _in_ray: &Ray, _hit: &RayHit, _direction: &Vec3) -> f32 {
+        0.0
+    }
+
+    fn emitted(&self, _hit: &RayHit) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    /// True for the phase functions of participating media, which scatter light at points
+    /// inside a volume rather than on a surface, so have no meaningful normal.
+    fn is_volumetric(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{f32::consts, sync::Arc};
+
+    use super::Material;
+    use crate::{
+        hitable::RayHit,
+        random,
+        structures::{Ray, Vec3},
+    };
+
+    /// A ray arriving at the origin from `elevation` degrees above the xz plane, and its
+    /// hit on a floor facing up the y axis.
+    pub fn floor_hit(material: Arc<dyn Material>, elevation: f32) -> (Ray, RayHit) {
+        let radians = elevation.to_radians();
+        let direction = Vec3::new(radians.cos(), -radians.sin(), 0.0);
+        let ray = Ray::new(-direction, direction, 0.0);
+        let hit = RayHit {
+            distance: 1.0,
+            point: Vec3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            u: 0.5,
+            v: 0.5,
+            material,
+        };
+        (ray, hit)
+    }
+
+    /// Integrates `eval` over the whole sphere of directions with the midpoint rule, giving
+    /// the fraction of light the material reflects or transmits.
+    pub fn integrate_eval(ray: &Ray, hit: &RayHit) -> Vec3 {
+        const THETA_STEPS: usize = 512;
+        const PHI_STEPS: usize = 1024;
+        let d_theta = consts::PI / THETA_STEPS as f32;
+        let d_phi = 2.0 * consts::PI / PHI_STEPS as f32;
+        let mut total = Vec3::new(0.0, 0.0, 0.0);
+        for i in 0..THETA_STEPS {
+            let theta = (i as f32 + 0.5) * d_theta;
+            let solid_angle = theta.sin() * d_theta * d_phi;
+            for j in 0..PHI_STEPS {
+                let phi = (j as f32 + 0.5) * d_phi;
+                let direction = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                total += hit.material.eval(ray, hit, &direction) * solid_angle;
+            }
+        }
+        total
+    }
+
+    /// Averages the weights of many samples, counting absorbed samples as zero, which
+    /// estimates the same fraction as `integrate_eval`. Also checks every sample's pdf
+    /// and weight agree with `pdf` and `eval`.
+    pub fn mean_sample_weight(ray: &Ray, hit: &RayHit, count: usize) -> Vec3 {
+        random::reseed(1);
+        let mut total = Vec3::new(0.0, 0.0, 0.0);
+        for _ in 0..count {
+            let sample = match hit.material.sample(ray, hit) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            if !sample.specular {
+                let pdf = hit.material.pdf(ray, hit, &sample.direction);
+                assert_close(sample.pdf, pdf, 1e-3);
+                let expected = hit.material.eval(ray, hit, &sample.direction) / pdf;
+                assert_vec3_close(sample.weight, expected, 1e-3);
+            }
+            total += sample.weight;
+        }
+        total / count as f32
+    }
+
+    pub fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs().max(1.0),
+            "expected {} to be within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    pub fn assert_vec3_close(actual: Vec3, expected: Vec3, tolerance: f32) {
+        for channel in 0..3 {
+            assert_close(actual[channel], expected[channel], tolerance);
+        }
+    }
 }
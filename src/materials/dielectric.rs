@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use crate::{
     hitable::RayHit,
-    material::{Material, MaterialHit},
+    material::{Material, MaterialSample},
+    random,
     structures::{Ray, Vec3},
 };
 
@@ -28,8 +29,8 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialHit> {
-        let mut rng = thread_rng();
+    fn sample(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialSample> {
+        let mut rng = random::rng();
         let reflected = in_ray.direction.relfect(&hit.normal);
         let attenuation = Vec3::new(1.0, 1.0, 1.0);
         let (outward_normal, ni_over_nt, cosine) = if in_ray.direction.dot(&hit.normal) > 0.0 {
@@ -48,14 +49,38 @@ impl Material for Dielectric {
             } else {
                 1.0
             };
-        let scatter_ray = if rng.gen::<f32>() < reflected_prob {
-            Ray::new(hit.point, reflected, in_ray.time)
+        let direction = if rng.gen::<f32>() < reflected_prob {
+            reflected
         } else {
-            Ray::new(hit.point, refraction_dir, in_ray.time)
+            refraction_dir
         };
-        Some(MaterialHit {
-            attenuation,
-            scatter_ray,
+        Some(MaterialSample {
+            direction,
+            weight: attenuation,
+            pdf: 0.0,
+            specular: true,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Dielectric;
+    use crate::{
+        material::tests::{assert_vec3_close, floor_hit, mean_sample_weight},
+        structures::Vec3,
+    };
+
+    // Glass reflects or refracts everything, so its albedo is one
+    #[test]
+    fn samples_conserve_energy() {
+        for elevation in [5.0, 45.0, 90.0] {
+            let (ray, hit) = floor_hit(Dielectric::arc(1.5), elevation);
+            for _ in 0..100 {
+                assert!(hit.material.sample(&ray, &hit).unwrap().specular);
+            }
+            let expected = Vec3::new(1.0, 1.0, 1.0);
+            assert_vec3_close(mean_sample_weight(&ray, &hit, 1_000), expected, 1e-6);
+        }
+    }
+}
@@ -1,33 +1,79 @@
-use std::sync::Arc;
+use std::{f32::consts, sync::Arc};
 
 use crate::{
     hitable::RayHit,
-    material::{Material, MaterialHit},
+    material::{Material, MaterialSample},
     structures::{Ray, Vec3},
+    texture::Texture,
+    textures::ConstantTexture,
 };
 
 pub struct Diffuse {
-    albedo: Vec3,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Diffuse {
     pub fn new(albedo: Vec3) -> Self {
-        Self { albedo }
+        Self::textured(ConstantTexture::arc(albedo))
     }
 
     pub fn arc(albedo: Vec3) -> Arc<dyn Material> {
         Arc::new(Self::new(albedo))
     }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+
+    pub fn textured_arc(albedo: Arc<dyn Texture>) -> Arc<dyn Material> {
+        Arc::new(Self::textured(albedo))
+    }
 }
 
 impl Material for Diffuse {
-    fn scatter(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialHit> {
-        let target = hit.normal + Vec3::get_point_in_unit_sphere();
-        let scatter_ray = Ray::new(hit.point, target, in_ray.time);
-        let attenuation = self.albedo;
-        Some(MaterialHit {
-            attenuation,
-            scatter_ray,
+    fn sample(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialSample> {
+        // Offsetting the normal by a point on the unit sphere gives a cosine distribution
+        let mut direction = hit.normal + Vec3::get_point_on_unit_sphere();
+        if direction.length_squared() < 1e-8 {
+            direction = hit.normal;
+        }
+        // The cosine and 1/pi in eval cancel with the pdf, leaving just the albedo
+        Some(MaterialSample {
+            direction,
+            weight: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf: self.pdf(in_ray, hit, &direction),
+            specular: false,
         })
     }
+
+    fn eval(&self, in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.point) * self.pdf(in_ray, hit, direction)
+    }
+
+    fn pdf(&self, _in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> f32 {
+        f32::max(hit.normal.dot(&direction.unit()), 0.0) / consts::PI
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Diffuse;
+    use crate::{
+        material::tests::{assert_vec3_close, floor_hit, integrate_eval, mean_sample_weight},
+        structures::Vec3,
+    };
+
+    #[test]
+    fn integrates_to_albedo() {
+        let albedo = Vec3::new(0.5, 0.25, 0.8);
+        let (ray, hit) = floor_hit(Diffuse::arc(albedo), 40.0);
+        assert_vec3_close(integrate_eval(&ray, &hit), albedo, 1e-3);
+    }
+
+    #[test]
+    fn samples_are_weighted_by_albedo() {
+        let albedo = Vec3::new(0.5, 0.25, 0.8);
+        let (ray, hit) = floor_hit(Diffuse::arc(albedo), 40.0);
+        assert_vec3_close(mean_sample_weight(&ray, &hit, 10_000), albedo, 1e-3);
+    }
 }
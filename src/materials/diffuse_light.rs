@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::RayHit,
+    material::{Material, MaterialSample},
+    structures::{Ray, Vec3},
+};
+
+pub struct DiffuseLight {
+    emit: Vec3,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> Self {
+        Self { emit }
+    }
+
+    pub fn arc(emit: Vec3) -> Arc<dyn Material> {
+        Arc::new(Self::new(emit))
+    }
+}
+
+impl Material for DiffuseLight {
+    fn sample(&self, _in_ray: &Ray, _hit: &RayHit) -> Option<MaterialSample> {
+        None
+    }
+
+    fn emitted(&self, _hit: &RayHit) -> Vec3 {
+        self.emit
+    }
+}
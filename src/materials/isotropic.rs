@@ -0,0 +1,103 @@
+use std::{f32::consts, sync::Arc};
+
+use crate::{
+    hitable::RayHit,
+    material::{Material, MaterialSample},
+    structures::{Ray, Vec3},
+    texture::Texture,
+    textures::ConstantTexture,
+};
+
+/// Phase function of a participating medium, which scatters equally in every direction.
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vec3) -> Self {
+        Self::textured(ConstantTexture::arc(albedo))
+    }
+
+    pub fn arc(albedo: Vec3) -> Arc<dyn Material> {
+        Arc::new(Self::new(albedo))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+
+    pub fn textured_arc(albedo: Arc<dyn Texture>) -> Arc<dyn Material> {
+        Arc::new(Self::textured(albedo))
+    }
+}
+
+impl Material for Isotropic {
+    fn sample(&self, _in_ray: &Ray, hit: &RayHit) -> Option<MaterialSample> {
+        Some(MaterialSample {
+            direction: Vec3::get_point_on_unit_sphere(),
+            weight: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf: 1.0 / (4.0 * consts::PI),
+            specular: false,
+        })
+    }
+
+    // There is no surface, so no cosine term
+    fn eval(&self, in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> Vec3 {
+        self.albedo.value(hit.u, hit.v, &hit.point) * self.pdf(in_ray, hit, direction)
+    }
+
+    fn pdf(&self, _in_ray: &Ray, _hit: &RayHit, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * consts::PI)
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts;
+
+    use super::Isotropic;
+    use crate::{
+        hitable::RayHit,
+        material::tests::{assert_vec3_close, integrate_eval, mean_sample_weight},
+        random,
+        structures::{Ray, Vec3},
+    };
+
+    // A collision inside a medium, which has no surface, so its normal means nothing
+    fn medium_hit(albedo: Vec3, direction: Vec3) -> (Ray, RayHit) {
+        let ray = Ray::new(-direction, direction, 0.0);
+        let hit = RayHit {
+            distance: 1.0,
+            point: Vec3::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            u: 0.5,
+            v: 0.5,
+            material: Isotropic::arc(albedo),
+        };
+        (ray, hit)
+    }
+
+    #[test]
+    fn integrates_to_albedo() {
+        let albedo = Vec3::new(0.2, 0.4, 0.9);
+        let (ray, hit) = medium_hit(albedo, Vec3::new(0.0, 0.0, -1.0));
+        assert_vec3_close(integrate_eval(&ray, &hit), albedo, 1e-3);
+        assert_vec3_close(mean_sample_weight(&ray, &hit, 10_000), albedo, 1e-3);
+    }
+
+    #[test]
+    fn scatters_equally_in_every_direction() {
+        let albedo = Vec3::new(0.2, 0.4, 0.9);
+        let expected = albedo / (4.0 * consts::PI);
+        random::reseed(1);
+        for _ in 0..100 {
+            let (ray, hit) = medium_hit(albedo, Vec3::get_point_on_unit_sphere());
+            let direction = Vec3::get_point_on_unit_sphere();
+            assert_vec3_close(hit.material.eval(&ray, &hit, &direction), expected, 1e-6);
+        }
+    }
+}
@@ -1,45 +1,118 @@
-use std::sync::Arc;
+use std::{f32::consts, sync::Arc};
 
 use crate::{
     hitable::RayHit,
-    material::{Material, MaterialHit},
+    material::{Material, MaterialSample},
     structures::{Ray, Vec3},
+    texture::Texture,
+    textures::ConstantTexture,
 };
 
 pub struct Metal {
-    albedo: Vec3,
+    albedo: Arc<dyn Texture>,
     fuzz: f32,
 }
 
 impl Metal {
     pub fn new(albedo: Vec3, fuzz: f32) -> Self {
+        Self::textured(ConstantTexture::arc(albedo), fuzz)
+    }
+
+    pub fn arc(albedo: Vec3, fuzz: f32) -> Arc<dyn Material> {
+        Arc::new(Self::new(albedo, fuzz))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzz: f32) -> Self {
         Self {
             albedo,
             fuzz: fuzz.clamp(0.0, 1.0),
         }
     }
 
-    pub fn arc(albedo: Vec3, fuzz: f32) -> Arc<dyn Material> {
-        Arc::new(Self::new(albedo, fuzz))
+    pub fn textured_arc(albedo: Arc<dyn Texture>, fuzz: f32) -> Arc<dyn Material> {
+        Arc::new(Self::textured(albedo, fuzz))
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialHit> {
+    fn sample(&self, in_ray: &Ray, hit: &RayHit) -> Option<MaterialSample> {
         let reflection = in_ray.direction.unit().relfect(&hit.normal);
-        let scatter_ray = Ray::new(
-            hit.point,
-            reflection + self.fuzz * Vec3::get_point_in_unit_sphere(),
-            in_ray.time,
-        );
-        let attenuation = self.albedo;
-        if scatter_ray.direction.dot(&hit.normal) > 0.0 {
-            Some(MaterialHit {
-                attenuation,
-                scatter_ray,
-            })
+        let direction = reflection + self.fuzz * Vec3::get_point_in_unit_sphere();
+        // Directions fuzzed below the surface are absorbed
+        if direction.dot(&hit.normal) <= 0.0 {
+            return None;
+        }
+        // eval is the albedo times the pdf, so the weight is just the albedo
+        Some(MaterialSample {
+            direction,
+            weight: self.albedo.value(hit.u, hit.v, &hit.point),
+            pdf: self.pdf(in_ray, hit, &direction),
+            specular: self.fuzz <= 0.0,
+        })
+    }
+
+    fn eval(&self, in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> Vec3 {
+        if direction.dot(&hit.normal) > 0.0 {
+            self.albedo.value(hit.u, hit.v, &hit.point) * self.pdf(in_ray, hit, direction)
         } else {
-            None
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    fn pdf(&self, in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> f32 {
+        if self.fuzz <= 0.0 {
+            return 0.0;
+        }
+        // Scattered directions point at a uniformly chosen point of the fuzz sphere around the
+        // reflection, so the density is the volume of that sphere seen along the direction.
+        let reflection = in_ray.direction.unit().relfect(&hit.normal);
+        let direction = direction.unit();
+        let b = direction.dot(&reflection);
+        let discriminant = b.powi(2) - reflection.length_squared() + self.fuzz.powi(2);
+        if discriminant <= 0.0 {
+            return 0.0;
         }
+        let near = f32::max(b - discriminant.sqrt(), 0.0);
+        let far = f32::max(b + discriminant.sqrt(), 0.0);
+        (far.powi(3) - near.powi(3)) / (4.0 * consts::PI * self.fuzz.powi(3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metal;
+    use crate::{
+        material::tests::{assert_vec3_close, floor_hit, integrate_eval, mean_sample_weight},
+        structures::Vec3,
+    };
+
+    // The fuzz sphere around the reflection stays above the surface, so nothing is absorbed
+    #[test]
+    fn fuzzy_metal_integrates_to_albedo() {
+        let albedo = Vec3::new(0.9, 0.6, 0.3);
+        let (ray, hit) = floor_hit(Metal::arc(albedo, 0.3), 60.0);
+        assert_vec3_close(integrate_eval(&ray, &hit), albedo, 1e-2);
+        assert_vec3_close(mean_sample_weight(&ray, &hit, 10_000), albedo, 1e-3);
+    }
+
+    // Fuzzed directions below the surface are absorbed, by both sampling and eval
+    #[test]
+    fn grazing_fuzzy_metal_loses_light_consistently() {
+        let albedo = Vec3::new(0.9, 0.6, 0.3);
+        let (ray, hit) = floor_hit(Metal::arc(albedo, 0.8), 10.0);
+        let integral = integrate_eval(&ray, &hit);
+        assert!(integral.x() < 0.9 * albedo.x());
+        assert_vec3_close(mean_sample_weight(&ray, &hit, 100_000), integral, 1e-2);
+    }
+
+    #[test]
+    fn polished_metal_is_specular() {
+        let albedo = Vec3::new(0.9, 0.6, 0.3);
+        let (ray, hit) = floor_hit(Metal::arc(albedo, 0.0), 30.0);
+        let sample = hit.material.sample(&ray, &hit).unwrap();
+        assert!(sample.specular);
+        assert_eq!(sample.pdf, 0.0);
+        assert_vec3_close(sample.weight, albedo, 1e-6);
+        assert_vec3_close(sample.direction, ray.direction.relfect(&hit.normal), 1e-6);
     }
 }
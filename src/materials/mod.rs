@@ -1,7 +1,11 @@
 mod dielectric;
 mod diffuse;
+mod diffuse_light;
+mod isotropic;
 mod metal;
 
 pub use dielectric::*;
 pub use diffuse::*;
+pub use diffuse_light::*;
+pub use isotropic::*;
 pub use metal::*;
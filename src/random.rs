@@ -0,0 +1,74 @@
+use std::{any::Any, cell::RefCell};
+
+use rand::{rngs::SmallRng, RngCore, SeedableRng};
+
+thread_local! {
+    static SAMPLER: RefCell<Box<dyn Sampler>> = RefCell::new(Box::new(SmallRng::from_entropy()));
+}
+
+/// Source of every random number drawn while rendering. Each thread normally draws from
+/// its own seeded generator, but an integrator can install another for a while, as
+/// Metropolis light transport does to choose the numbers each path is traced with.
+pub trait Sampler: Any {
+    fn next_u32(&mut self) -> u32;
+
+    fn next_u64(&mut self) -> u64;
+}
+
+impl Sampler for SmallRng {
+    fn next_u32(&mut self) -> u32 {
+        RngCore::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RngCore::next_u64(self)
+    }
+}
+
+/// Handle to the current thread's sampler, used in place of `rand::thread_rng`
+/// so that renders can be reproduced from a seed.
+#[derive(Clone, Copy)]
+pub struct LocalRng;
+
+pub fn rng() -> LocalRng {
+    LocalRng
+}
+
+pub fn reseed(seed: u64) {
+    SAMPLER.with(|sampler| *sampler.borrow_mut() = Box::new(SmallRng::seed_from_u64(seed)));
+}
+
+/// Runs `f` with every number drawn through `rng` on this thread coming from `sampler`,
+/// then hands the sampler back along with the result. The thread's previous sampler
+/// carries on from where it was afterwards.
+pub fn with_sampler<S: Sampler, R>(sampler: S, f: impl FnOnce() -> R) -> (S, R) {
+    let previous = SAMPLER.with(|current| current.replace(Box::new(sampler)));
+    let result = f();
+    let sampler: Box<dyn Any> = SAMPLER.with(|current| current.replace(previous));
+    match sampler.downcast::<S>() {
+        Ok(sampler) => (*sampler, result),
+        Err(_) => panic!("The sampler was replaced while in use"),
+    }
+}
+
+impl RngCore for LocalRng {
+    fn next_u32(&mut self) -> u32 {
+        SAMPLER.with(|sampler| sampler.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        SAMPLER.with(|sampler| sampler.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io,
+    mem::size_of,
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use image::{png::PngEncoder, ImageError};
+use rand::Rng;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    camera::Camera, environment::Environment, hitable::Hitable, integrator::Integrator, random,
+    structures::Vec3,
+};
+
+#[derive(Clone)]
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub seed: u64,
+    pub environment: Arc<dyn Environment>,
+    // Emissive shapes which are sampled directly, as well as being found by scattering
+    pub lights: Vec<Arc<dyn Hitable>>,
+    pub integrator: Arc<dyn Integrator>,
+}
+
+/// Linear colour values averaged over all samples, stored row by row from the top left.
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "A framebuffer must have one colour per pixel"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Gamma corrected 16 bit RGB, with big endian channels as expected by PNG.
+    pub fn to_rgb16_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * size_of::<u16>() * 3);
+        for col in &self.pixels {
+            let col = Vec3::new(col[0].sqrt(), col[1].sqrt(), col[2].sqrt());
+            let hdr_rgb = [
+                (65534.99 * col.r()) as u16,
+                (65534.99 * col.g()) as u16,
+                (65534.99 * col.b()) as u16,
+            ];
+            bytes.extend(hdr_rgb.iter().flat_map(|channel| channel.to_be_bytes()));
+        }
+        bytes
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let file = File::create(path).map_err(ImageError::IoError)?;
+        self.write_png(file)
+    }
+
+    pub fn write_png(&self, writer: impl io::Write) -> Result<(), ImageError> {
+        PngEncoder::new(writer).encode(
+            &self.to_rgb16_bytes(),
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgb16,
+        )
+    }
+}
+
+/// Light carried to pixels other than the one being sampled, as by paths traced from the
+/// lights to the camera. Nothing is allocated until the first splat.
+pub struct Splats {
+    width: usize,
+    height: usize,
+    // Colour channels stored as f32 bits, so threads can add to them without locking
+    pixels: OnceLock<Vec<[AtomicU32; 3]>>,
+}
+
+impl Splats {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: OnceLock::new(),
+        }
+    }
+
+    /// Adds light landing on the image at `x` and `y`, as passed to `Camera::get_ray`.
+    /// Light landing outside the image is dropped.
+    pub fn add(&self, x: f32, y: f32, colour: Vec3) {
+        let column = (x * self.width as f32).floor();
+        // Matches the rows render steps through, counting down from the top
+        let row = self.height as f32 - (y * self.height as f32).floor();
+        if !(column >= 0.0 && column < self.width as f32 && row >= 0.0 && row < self.height as f32)
+        {
+            return;
+        }
+        let pixels = self.pixels.get_or_init(|| {
+            (0..self.width * self.height)
+                .map(|_| Default::default())
+                .collect()
+        });
+        let pixel = &pixels[row as usize * self.width + column as usize];
+        for (channel, value) in pixel.iter().zip([colour.r(), colour.g(), colour.b()]) {
+            // Never fails, as the closure always returns a value
+            let _ = channel.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f32::from_bits(bits) + value).to_bits())
+            });
+        }
+    }
+
+    /// The light splatted so far, multiplied by `scale`, which can be taken while other
+    /// threads are still adding to it.
+    pub fn framebuffer(&self, scale: f32) -> Framebuffer {
+        let pixels = (0..self.width * self.height)
+            .map(|idx| self.pixel(idx) * scale)
+            .collect();
+        Framebuffer::new(self.width, self.height, pixels)
+    }
+
+    fn pixel(&self, idx: usize) -> Vec3 {
+        match self.pixels.get() {
+            Some(pixels) => {
+                let [r, g, b] = &pixels[idx];
+                Vec3::new(
+                    f32::from_bits(r.load(Ordering::Relaxed)),
+                    f32::from_bits(g.load(Ordering::Relaxed)),
+                    f32::from_bits(b.load(Ordering::Relaxed)),
+                )
+            }
+            None => Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+/// Renders the world in parallel on the current rayon pool.
+pub fn render(world: &dyn Hitable, camera: &Camera, settings: &RenderSettings) -> Framebuffer {
+    if let Some(framebuffer) = settings.integrator.render(world, camera, settings) {
+        return framebuffer;
+    }
+    let nx = settings.width;
+    let ny = settings.height;
+    let splats = Splats::new(nx, ny);
+    let pixels: Vec<Vec3> = (0..nx * ny)
+        .into_par_iter()
+        .map(|idx| {
+            // Seeding per pixel keeps renders reproducible however rayon schedules the work
+            random::reseed(settings.seed ^ (idx as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let mut rng = random::rng();
+            let j = ny - idx / nx;
+            let i = idx % nx;
+            let mut col = Vec3::new(0.0, 0.0, 0.0);
+            for _ in 0..settings.samples {
+                let u_jitter: f32 = rng.gen();
+                let v_jitter: f32 = rng.gen();
+                let u = (i as f32 + u_jitter) / nx as f32;
+                let v = (j as f32 + v_jitter) / ny as f32;
+                let ray = camera.get_ray(u, v);
+                col += settings
+                    .integrator
+                    .radiance(&ray, world, camera, settings, &splats);
+            }
+            col / settings.samples as f32
+        })
+        .collect();
+    // Splats arrive in whatever order threads finish, so unlike the pixels themselves
+    // they may differ in rounding between renders
+    let pixels = pixels
+        .into_iter()
+        .enumerate()
+        .map(|(idx, col)| col + splats.pixel(idx) / settings.samples as f32)
+        .collect();
+    Framebuffer::new(nx, ny, pixels)
+}
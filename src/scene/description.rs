@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::structures::Vec3;
+
+#[derive(Deserialize, Clone, Copy)]
+pub struct Vec3Description([f32; 3]);
+
+impl From<Vec3Description> for Vec3 {
+    fn from(description: Vec3Description) -> Self {
+        let [x, y, z] = description.0;
+        Vec3::new(x, y, z)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub image: ImageDescription,
+    pub camera: CameraDescription,
+    // Defaults to the sky gradient
+    pub environment: Option<EnvironmentDescription>,
+    #[serde(default)]
+    pub textures: HashMap<String, TextureDescription>,
+    pub materials: HashMap<String, MaterialDescription>,
+    // Named groups of shapes, each built into its own BVH, which can be placed many times
+    // with instance shapes
+    #[serde(default)]
+    pub objects: HashMap<String, Vec<ShapeDescription>>,
+    pub shapes: Vec<ShapeDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageDescription {
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub position: Vec3Description,
+    pub look_at: Vec3Description,
+    #[serde(default = "default_up")]
+    pub up: Vec3Description,
+    pub vertical_fov: f32,
+    // Defaults to the aspect ratio of the image
+    pub aspect: Option<f32>,
+    #[serde(default)]
+    pub aperture: f32,
+    // Defaults to the distance between position and look_at
+    pub focus_distance: Option<f32>,
+    #[serde(default)]
+    pub time0: f32,
+    #[serde(default)]
+    pub time1: f32,
+}
+
+fn default_up() -> Vec3Description {
+    Vec3Description([0.0, 1.0, 0.0])
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum EnvironmentDescription {
+    Solid {
+        colour: Vec3Description,
+    },
+    Gradient {
+        bottom: Vec3Description,
+        top: Vec3Description,
+    },
+    Map {
+        path: String,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        // Degrees about the vertical axis
+        #[serde(default)]
+        rotation: f32,
+    },
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureDescription {
+    Constant {
+        colour: Vec3Description,
+    },
+    Checker {
+        scale: f32,
+        even: Vec3Description,
+        odd: Vec3Description,
+    },
+    Image {
+        path: String,
+    },
+    Noise {
+        scale: f32,
+        colour: Vec3Description,
+        #[serde(default)]
+        seed: u64,
+    },
+    Turbulence {
+        scale: f32,
+        #[serde(default = "default_turbulence_depth")]
+        depth: u32,
+        colour: Vec3Description,
+        #[serde(default)]
+        seed: u64,
+    },
+    Marble {
+        scale: f32,
+        #[serde(default = "default_marble_distortion")]
+        distortion: f32,
+        colour: Vec3Description,
+        #[serde(default)]
+        seed: u64,
+    },
+    Wood {
+        // Rings per unit distance from the y axis
+        scale: f32,
+        #[serde(default = "default_wood_distortion")]
+        distortion: f32,
+        light: Vec3Description,
+        dark: Vec3Description,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+fn default_turbulence_depth() -> u32 {
+    7
+}
+
+fn default_marble_distortion() -> f32 {
+    10.0
+}
+
+fn default_wood_distortion() -> f32 {
+    1.0
+}
+
+// Either a colour, or the name of a texture
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum AlbedoDescription {
+    Colour(Vec3Description),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Diffuse {
+        albedo: AlbedoDescription,
+    },
+    Metal {
+        albedo: AlbedoDescription,
+        #[serde(default)]
+        fuzz: f32,
+    },
+    Dielectric {
+        refractive_index: f32,
+    },
+    DiffuseLight {
+        emit: Vec3Description,
+    },
+    // Phase function for constant_medium volumes
+    Isotropic {
+        albedo: AlbedoDescription,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ShapeDescription {
+    Sphere {
+        centre: Vec3Description,
+        radius: f32,
+        material: String,
+    },
+    MovingSphere {
+        centre0: Vec3Description,
+        centre1: Vec3Description,
+        time0: f32,
+        time1: f32,
+        radius: f32,
+        material: String,
+    },
+    Triangle {
+        vertices: [Vec3Description; 3],
+        material: String,
+    },
+    // A parallelogram with a corner at the origin and edges u and v
+    Quad {
+        origin: Vec3Description,
+        u: Vec3Description,
+        v: Vec3Description,
+        material: String,
+    },
+    XyRect {
+        x0: f32,
+        x1: f32,
+        y0: f32,
+        y1: f32,
+        // Position along the axis the rectangle faces
+        k: f32,
+        material: String,
+    },
+    XzRect {
+        x0: f32,
+        x1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    YzRect {
+        y0: f32,
+        y1: f32,
+        z0: f32,
+        z1: f32,
+        k: f32,
+        material: String,
+    },
+    // An axis aligned box between two opposite corners
+    Cuboid {
+        min: Vec3Description,
+        max: Vec3Description,
+        material: String,
+    },
+    // A volume of smoke or fog filling a closed boundary shape
+    ConstantMedium {
+        boundary: Box<ShapeDescription>,
+        density: f32,
+        material: String,
+    },
+    // A cloud or explosion from a voxel grid file, stretched over the box from min to max
+    VoxelVolume {
+        path: String,
+        min: Vec3Description,
+        max: Vec3Description,
+        // Multiplies the grid's densities
+        #[serde(default = "default_volume_density")]
+        density: f32,
+        #[serde(default = "default_volume_albedo")]
+        albedo: Vec3Description,
+        // Luminance of the hottest voxel, which needs a grid with temperatures
+        #[serde(default)]
+        emission: f32,
+        // Multiplies the grid's temperatures to give kelvin
+        #[serde(default = "default_temperature_scale")]
+        temperature_scale: f32,
+    },
+    // Meshes take their materials from the MTL files referenced by the OBJ
+    Mesh {
+        path: String,
+    },
+    Instance {
+        object: String,
+        #[serde(default)]
+        transform: Vec<TransformStep>,
+    },
+    // Moves the shapes, as a single group, by each step of the transform in turn
+    Transform {
+        transform: Vec<TransformStep>,
+        shapes: Vec<ShapeDescription>,
+    },
+}
+
+impl ShapeDescription {
+    // The material of shapes which can be sampled as lights
+    pub fn light_material(&self) -> Option<&str> {
+        match self {
+            ShapeDescription::Sphere { material, .. }
+            | ShapeDescription::Triangle { material, .. }
+            | ShapeDescription::Quad { material, .. }
+            | ShapeDescription::XyRect { material, .. }
+            | ShapeDescription::XzRect { material, .. }
+            | ShapeDescription::YzRect { material, .. } => Some(material),
+            _ => None,
+        }
+    }
+}
+
+fn default_volume_density() -> f32 {
+    1.0
+}
+
+fn default_volume_albedo() -> Vec3Description {
+    Vec3Description([1.0, 1.0, 1.0])
+}
+
+fn default_temperature_scale() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum TransformStep {
+    Translate(Vec3Description),
+    // Degrees, counterclockwise when looking back down the axis
+    RotateX(f32),
+    RotateY(f32),
+    RotateZ(f32),
+    Scale(ScaleDescription),
+    // Row major affine matrix
+    Matrix([[f32; 4]; 4]),
+}
+
+// Either a single factor for every axis, or one per axis
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum ScaleDescription {
+    Uniform(f32),
+    PerAxis(Vec3Description),
+}
+
+impl From<ScaleDescription> for Vec3 {
+    fn from(description: ScaleDescription) -> Self {
+        match description {
+            ScaleDescription::Uniform(factor) => Vec3::new(factor, factor, factor),
+            ScaleDescription::PerAxis(factors) => factors.into(),
+        }
+    }
+}
@@ -0,0 +1,1007 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::Path,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use image::ImageError;
+
+use crate::{
+    camera::Camera,
+    environment::Environment,
+    environments::{EnvironmentMap, Gradient, SolidColour},
+    hitable::Hitable,
+    loaders::{load_obj, load_voxel_grid, ObjError, VoxelGridError},
+    material::Material,
+    materials::{Dielectric, Diffuse, DiffuseLight, Isotropic, Metal},
+    shapes::{
+        ConstantMedium, Cuboid, Instance, MovingSphere, Quad, RotateX, RotateY, RotateZ, Scale,
+        Sphere, Transform, Translate, Triangle, VoxelVolume, XyRect, XzRect, YzRect,
+    },
+    structures::{BvhBuildProgress, BvhNode, Matrix4, Vec3},
+    texture::Texture,
+    textures::{
+        CheckerTexture, ConstantTexture, ImageTexture, MarbleTexture, NoiseTexture,
+        TurbulenceTexture, WoodTexture,
+    },
+};
+
+mod description;
+mod random_scene;
+
+use description::{
+    AlbedoDescription, EnvironmentDescription, MaterialDescription, SceneDescription,
+    ShapeDescription, TextureDescription, TransformStep,
+};
+
+pub use random_scene::*;
+
+#[derive(Default)]
+pub struct ImageOverrides {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples: Option<usize>,
+}
+
+pub struct Scene {
+    pub world: BvhNode,
+    pub camera: Camera,
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    pub environment: Arc<dyn Environment>,
+    pub lights: Vec<Arc<dyn Hitable>>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        field: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Invalid {
+        path: PathBuf,
+        field: String,
+        message: String,
+    },
+    Mesh {
+        path: PathBuf,
+        field: String,
+        source: ObjError,
+    },
+    Image {
+        path: PathBuf,
+        field: String,
+        source: ImageError,
+    },
+    Volume {
+        path: PathBuf,
+        field: String,
+        source: VoxelGridError,
+    },
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            SceneError::Parse {
+                path,
+                field,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "{}:{}:{}: {}: {}",
+                path.display(),
+                line,
+                column,
+                field,
+                message
+            ),
+            SceneError::Invalid {
+                path,
+                field,
+                message,
+            } => write!(f, "{}: {}: {}", path.display(), field, message),
+            SceneError::Mesh {
+                path,
+                field,
+                source,
+            } => write!(f, "{}: {}: {}", path.display(), field, source),
+            SceneError::Image {
+                path,
+                field,
+                source,
+            } => write!(f, "{}: {}: {}", path.display(), field, source),
+            SceneError::Volume {
+                path,
+                field,
+                source,
+            } => write!(f, "{}: {}: {}", path.display(), field, source),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Mesh { source, .. } => Some(source),
+            SceneError::Image { source, .. } => Some(source),
+            SceneError::Volume { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Loads a JSON scene description, resolving mesh paths relative to the scene file.
+/// Any overridden image settings replace those in the file before the camera is built.
+pub fn load_scene(path: impl AsRef<Path>, overrides: &ImageOverrides) -> Result<Scene, SceneError> {
+    load_scene_with_progress(path, overrides, |_| {})
+}
+
+/// As [`load_scene`], calling `progress` as each level of the scene's BVH is built.
+pub fn load_scene_with_progress(
+    path: impl AsRef<Path>,
+    overrides: &ImageOverrides,
+    progress: impl FnMut(&BvhBuildProgress),
+) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_scene(path, &source, overrides, progress)
+}
+
+// Builds the scene from the contents of the file at `path`, which is only read from for
+// the meshes, images and grids it refers to
+fn parse_scene(
+    path: &Path,
+    source: &str,
+    overrides: &ImageOverrides,
+    progress: impl FnMut(&BvhBuildProgress),
+) -> Result<Scene, SceneError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(source);
+    let mut description: SceneDescription = serde_path_to_error::deserialize(deserializer)
+        .map_err(|error| {
+            let field = error.path().to_string();
+            let inner = error.inner();
+            let (line, column) = (inner.line(), inner.column());
+            // serde_json appends the location to its messages, which we report separately
+            let message = inner.to_string();
+            let location = format!(" at line {} column {}", line, column);
+            SceneError::Parse {
+                path: path.to_path_buf(),
+                field,
+                line,
+                column,
+                message: message
+                    .strip_suffix(&location)
+                    .unwrap_or(&message)
+                    .to_string(),
+            }
+        })?;
+    let image = &mut description.image;
+    image.width = overrides.width.unwrap_or(image.width);
+    image.height = overrides.height.unwrap_or(image.height);
+    image.samples = overrides.samples.unwrap_or(image.samples);
+    build_scene(description, path, progress)
+}
+
+fn build_scene(
+    description: SceneDescription,
+    path: &Path,
+    progress: impl FnMut(&BvhBuildProgress),
+) -> Result<Scene, SceneError> {
+    let invalid = |field: String, message: &str| SceneError::Invalid {
+        path: path.to_path_buf(),
+        field,
+        message: message.to_string(),
+    };
+
+    let image = &description.image;
+    if image.width == 0 || image.height == 0 {
+        return Err(invalid(
+            "image".to_string(),
+            "width and height must be greater than zero",
+        ));
+    }
+    if image.samples == 0 {
+        return Err(invalid(
+            "image.samples".to_string(),
+            "must be greater than zero",
+        ));
+    }
+
+    let scene_directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut textures: HashMap<&str, Arc<dyn Texture>> = HashMap::new();
+    for (name, texture) in &description.textures {
+        let texture = match texture {
+            TextureDescription::Constant { colour } => ConstantTexture::arc((*colour).into()),
+            TextureDescription::Checker { scale, even, odd } => {
+                if *scale <= 0.0 {
+                    return Err(invalid(
+                        format!("textures.{}.scale", name),
+                        "must be greater than zero",
+                    ));
+                }
+                CheckerTexture::arc(
+                    *scale,
+                    ConstantTexture::arc((*even).into()),
+                    ConstantTexture::arc((*odd).into()),
+                )
+            }
+            TextureDescription::Image { path: image_path } => {
+                ImageTexture::arc(scene_directory.join(image_path)).map_err(|source| {
+                    SceneError::Image {
+                        path: path.to_path_buf(),
+                        field: format!("textures.{}.path", name),
+                        source,
+                    }
+                })?
+            }
+            TextureDescription::Noise {
+                scale,
+                colour,
+                seed,
+            } => NoiseTexture::arc(*seed, *scale, (*colour).into()),
+            TextureDescription::Turbulence {
+                scale,
+                depth,
+                colour,
+                seed,
+            } => {
+                if *depth == 0 {
+                    return Err(invalid(
+                        format!("textures.{}.depth", name),
+                        "must be greater than zero",
+                    ));
+                }
+                TurbulenceTexture::arc(*seed, *scale, *depth, (*colour).into())
+            }
+            TextureDescription::Marble {
+                scale,
+                distortion,
+                colour,
+                seed,
+            } => MarbleTexture::arc(*seed, *scale, *distortion, (*colour).into()),
+            TextureDescription::Wood {
+                scale,
+                distortion,
+                light,
+                dark,
+                seed,
+            } => WoodTexture::arc(*seed, *scale, *distortion, (*light).into(), (*dark).into()),
+        };
+        textures.insert(name, texture);
+    }
+
+    let mut materials: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+    let mut emissive_materials = HashSet::new();
+    for (name, material) in &description.materials {
+        let albedo = |albedo: &AlbedoDescription| match albedo {
+            AlbedoDescription::Colour(colour) => Ok(ConstantTexture::arc((*colour).into())),
+            AlbedoDescription::Texture(texture) => {
+                textures.get(texture.as_str()).cloned().ok_or_else(|| {
+                    invalid(
+                        format!("materials.{}.albedo", name),
+                        &format!("unknown texture '{}'", texture),
+                    )
+                })
+            }
+        };
+        let material = match material {
+            MaterialDescription::Diffuse { albedo: texture } => {
+                Diffuse::textured_arc(albedo(texture)?)
+            }
+            MaterialDescription::Metal {
+                albedo: texture,
+                fuzz,
+            } => Metal::textured_arc(albedo(texture)?, *fuzz),
+            MaterialDescription::Dielectric { refractive_index } => {
+                if *refractive_index <= 0.0 {
+                    return Err(invalid(
+                        format!("materials.{}.refractive_index", name),
+                        "must be greater than zero",
+                    ));
+                }
+                Dielectric::arc(*refractive_index)
+            }
+            MaterialDescription::DiffuseLight { emit } => {
+                emissive_materials.insert(name.as_str());
+                DiffuseLight::arc((*emit).into())
+            }
+            MaterialDescription::Isotropic { albedo: texture } => {
+                Isotropic::textured_arc(albedo(texture)?)
+            }
+        };
+        materials.insert(name, material);
+    }
+
+    let (time0, time1) = (description.camera.time0, description.camera.time1);
+    let mut shapes = ShapeBuilder {
+        path,
+        scene_directory,
+        materials,
+        emissive_materials,
+        objects: HashMap::new(),
+        time0,
+        time1,
+    };
+    for (name, object) in &description.objects {
+        let field = format!("objects.{}", name);
+        let mut list = Vec::with_capacity(object.len());
+        shapes.build(object, &field, false, &mut list, None)?;
+        if list.is_empty() {
+            return Err(invalid(field, "an object must contain at least one shape"));
+        }
+        let object = Arc::new(BvhNode::new(&list[..], time0, time1));
+        shapes.objects.insert(name, object);
+    }
+
+    let mut list = Vec::<Arc<dyn Hitable>>::with_capacity(description.shapes.len());
+    let mut lights = Vec::new();
+    shapes.build(
+        &description.shapes,
+        "shapes",
+        true,
+        &mut list,
+        Some(&mut lights),
+    )?;
+    if list.is_empty() {
+        return Err(invalid(
+            "shapes".to_string(),
+            "a scene must contain at least one shape",
+        ));
+    }
+
+    let environment = match &description.environment {
+        None => Gradient::sky(),
+        Some(EnvironmentDescription::Solid { colour }) => SolidColour::arc((*colour).into()),
+        Some(EnvironmentDescription::Gradient { bottom, top }) => {
+            Gradient::arc((*bottom).into(), (*top).into())
+        }
+        Some(EnvironmentDescription::Map {
+            path: map_path,
+            intensity,
+            rotation,
+        }) => {
+            let map = EnvironmentMap::load(scene_directory.join(map_path)).map_err(|source| {
+                SceneError::Image {
+                    path: path.to_path_buf(),
+                    field: "environment.path".to_string(),
+                    source,
+                }
+            })?;
+            Arc::new(map.with_intensity(*intensity).with_rotation(*rotation))
+        }
+    };
+
+    let camera = &description.camera;
+    let position: Vec3 = camera.position.into();
+    let look_at: Vec3 = camera.look_at.into();
+    let camera = Camera::new(
+        position,
+        look_at,
+        camera.up.into(),
+        camera.vertical_fov,
+        camera
+            .aspect
+            .unwrap_or(image.width as f32 / image.height as f32),
+        camera.aperture,
+        camera
+            .focus_distance
+            .unwrap_or_else(|| (position - look_at).length()),
+        time0,
+        time1,
+    );
+
+    Ok(Scene {
+        world: BvhNode::with_progress(&list[..], time0, time1, progress),
+        camera,
+        width: image.width,
+        height: image.height,
+        samples: image.samples,
+        environment,
+        lights,
+    })
+}
+
+struct ShapeBuilder<'a> {
+    path: &'a Path,
+    scene_directory: &'a Path,
+    materials: HashMap<&'a str, Arc<dyn Material>>,
+    emissive_materials: HashSet<&'a str>,
+    objects: HashMap<&'a str, Arc<BvhNode>>,
+    time0: f32,
+    time1: f32,
+}
+
+impl ShapeBuilder<'_> {
+    fn build(
+        &self,
+        shapes: &[ShapeDescription],
+        field_prefix: &str,
+        allow_instances: bool,
+        list: &mut Vec<Arc<dyn Hitable>>,
+        mut lights: Option<&mut Vec<Arc<dyn Hitable>>>,
+    ) -> Result<(), SceneError> {
+        for (index, shape) in shapes.iter().enumerate() {
+            let field_prefix = format!("{}[{}]", field_prefix, index);
+            self.build_shape(
+                shape,
+                &field_prefix,
+                allow_instances,
+                list,
+                lights.as_deref_mut(),
+            )?;
+        }
+        Ok(())
+    }
+
+    // Emissive shapes are also added to the lights, when given, which should only be for
+    // shapes placed directly in the world, as lights are sampled in world space
+    fn build_shape(
+        &self,
+        shape: &ShapeDescription,
+        field_prefix: &str,
+        allow_instances: bool,
+        list: &mut Vec<Arc<dyn Hitable>>,
+        lights: Option<&mut Vec<Arc<dyn Hitable>>>,
+    ) -> Result<(), SceneError> {
+        let path = self.path;
+        let invalid = |field: String, message: &str| SceneError::Invalid {
+            path: path.to_path_buf(),
+            field,
+            message: message.to_string(),
+        };
+        let field = |name: &str| format!("{}.{}", field_prefix, name);
+        let material = |name: &String| {
+            self.materials
+                .get(name.as_str())
+                .cloned()
+                .ok_or_else(|| invalid(field("material"), &format!("unknown material '{}'", name)))
+        };
+        // Rectangles must have a positive extent along both of their axes
+        let span = |start: f32, end: f32, start_name: &str, end_name: &str| {
+            if end > start {
+                Ok(())
+            } else {
+                Err(invalid(
+                    field(end_name),
+                    &format!("must be greater than {}", start_name),
+                ))
+            }
+        };
+        match shape {
+            ShapeDescription::Sphere {
+                centre,
+                radius,
+                material: name,
+            } => {
+                if *radius <= 0.0 {
+                    return Err(invalid(field("radius"), "must be greater than zero"));
+                }
+                list.push(Sphere::arc((*centre).into(), *radius, material(name)?));
+            }
+            ShapeDescription::MovingSphere {
+                centre0,
+                centre1,
+                time0,
+                time1,
+                radius,
+                material: name,
+            } => {
+                if *radius <= 0.0 {
+                    return Err(invalid(field("radius"), "must be greater than zero"));
+                }
+                if time1 <= time0 {
+                    return Err(invalid(field("time1"), "must be later than time0"));
+                }
+                list.push(MovingSphere::arc(
+                    (*centre0).into(),
+                    (*centre1).into(),
+                    *time0,
+                    *time1,
+                    *radius,
+                    material(name)?,
+                ));
+            }
+            ShapeDescription::Triangle {
+                vertices: [v0, v1, v2],
+                material: name,
+            } => {
+                list.push(Triangle::arc(
+                    [(*v0).into(), (*v1).into(), (*v2).into()],
+                    material(name)?,
+                ));
+            }
+            ShapeDescription::Quad {
+                origin,
+                u,
+                v,
+                material: name,
+            } => {
+                let (u, v): (Vec3, Vec3) = ((*u).into(), (*v).into());
+                if u.cross(&v).length_squared() <= 0.0 {
+                    return Err(invalid(
+                        field("v"),
+                        "must not be parallel to u or have zero length",
+                    ));
+                }
+                list.push(Quad::arc((*origin).into(), u, v, material(name)?));
+            }
+            ShapeDescription::XyRect {
+                x0,
+                x1,
+                y0,
+                y1,
+                k,
+                material: name,
+            } => {
+                span(*x0, *x1, "x0", "x1")?;
+                span(*y0, *y1, "y0", "y1")?;
+                list.push(XyRect::arc(*x0, *x1, *y0, *y1, *k, material(name)?));
+            }
+            ShapeDescription::XzRect {
+                x0,
+                x1,
+                z0,
+                z1,
+                k,
+                material: name,
+            } => {
+                span(*x0, *x1, "x0", "x1")?;
+                span(*z0, *z1, "z0", "z1")?;
+                list.push(XzRect::arc(*x0, *x1, *z0, *z1, *k, material(name)?));
+            }
+            ShapeDescription::YzRect {
+                y0,
+                y1,
+                z0,
+                z1,
+                k,
+                material: name,
+            } => {
+                span(*y0, *y1, "y0", "y1")?;
+                span(*z0, *z1, "z0", "z1")?;
+                list.push(YzRect::arc(*y0, *y1, *z0, *z1, *k, material(name)?));
+            }
+            ShapeDescription::Cuboid {
+                min,
+                max,
+                material: name,
+            } => {
+                let (min, max): (Vec3, Vec3) = ((*min).into(), (*max).into());
+                for axis in 0..3 {
+                    if max[axis] <= min[axis] {
+                        return Err(invalid(
+                            field("max"),
+                            "must be greater than min along every axis",
+                        ));
+                    }
+                }
+                list.push(Cuboid::arc(min, max, material(name)?));
+            }
+            ShapeDescription::VoxelVolume {
+                path: grid_path,
+                min,
+                max,
+                density,
+                albedo,
+                emission,
+                temperature_scale,
+            } => {
+                let (min, max): (Vec3, Vec3) = ((*min).into(), (*max).into());
+                let albedo: Vec3 = (*albedo).into();
+                if (0..3).any(|axis| max[axis] <= min[axis]) {
+                    return Err(invalid(
+                        field("max"),
+                        "must be greater than min along every axis",
+                    ));
+                }
+                if *density <= 0.0 {
+                    return Err(invalid(field("density"), "must be greater than zero"));
+                }
+                if (0..3).any(|channel| !(0.0..=1.0).contains(&albedo[channel])) {
+                    return Err(invalid(field("albedo"), "must be between 0 and 1"));
+                }
+                if *emission < 0.0 {
+                    return Err(invalid(field("emission"), "must not be negative"));
+                }
+                if *temperature_scale <= 0.0 {
+                    return Err(invalid(
+                        field("temperature_scale"),
+                        "must be greater than zero",
+                    ));
+                }
+                let grid =
+                    load_voxel_grid(self.scene_directory.join(grid_path)).map_err(|source| {
+                        SceneError::Volume {
+                            path: path.to_path_buf(),
+                            field: field("path"),
+                            source,
+                        }
+                    })?;
+                if *emission > 0.0 && !grid.has_temperature() {
+                    return Err(invalid(
+                        field("emission"),
+                        "needs a grid with a temperature channel",
+                    ));
+                }
+                let volume = VoxelVolume::new(Arc::new(grid), min, max, *density, albedo);
+                list.push(if *emission > 0.0 {
+                    Arc::new(volume.with_emission(*emission, *temperature_scale))
+                } else {
+                    Arc::new(volume)
+                });
+            }
+            ShapeDescription::Mesh { path: mesh_path } => {
+                let meshes = load_obj(self.scene_directory.join(mesh_path)).map_err(|source| {
+                    SceneError::Mesh {
+                        path: path.to_path_buf(),
+                        field: field("path"),
+                        source,
+                    }
+                })?;
+                list.extend(meshes);
+            }
+            ShapeDescription::Instance { object, transform } => {
+                if !allow_instances {
+                    return Err(invalid(
+                        field("type"),
+                        "instances can only be placed in the scene's shapes",
+                    ));
+                }
+                let object = self.objects.get(object.as_str()).ok_or_else(|| {
+                    invalid(field("object"), &format!("unknown object '{}'", object))
+                })?;
+                let object_to_world = transform.iter().enumerate().try_fold(
+                    Matrix4::identity(),
+                    |matrix, (step_index, step)| {
+                        let step_field = field(&format!("transform[{}]", step_index));
+                        Ok(self.transform_matrix(step, &step_field)? * matrix)
+                    },
+                )?;
+                // Each step is invertible, but their product can still be too flat to invert
+                if object_to_world.inverse().is_none() {
+                    return Err(invalid(field("transform"), "must be invertible"));
+                }
+                list.push(Instance::arc(object.clone(), object_to_world));
+            }
+            ShapeDescription::ConstantMedium {
+                boundary,
+                density,
+                material: name,
+            } => {
+                if *density <= 0.0 {
+                    return Err(invalid(field("density"), "must be greater than zero"));
+                }
+                let mut boundaries = Vec::new();
+                self.build_shape(
+                    boundary,
+                    &field("boundary"),
+                    allow_instances,
+                    &mut boundaries,
+                    None,
+                )?;
+                // Meshes are the only shapes which can build to more or less than one hitable
+                let boundary = match boundaries.len() {
+                    0 => return Err(invalid(field("boundary"), "must not be empty")),
+                    1 => boundaries.pop().unwrap(),
+                    _ => Arc::new(BvhNode::new(&boundaries, self.time0, self.time1)),
+                };
+                list.push(ConstantMedium::arc(boundary, *density, material(name)?));
+            }
+            ShapeDescription::Transform { transform, shapes } => {
+                let mut children = Vec::with_capacity(shapes.len());
+                self.build(
+                    shapes,
+                    &field("shapes"),
+                    allow_instances,
+                    &mut children,
+                    None,
+                )?;
+                let mut child = match children.len() {
+                    0 => return Err(invalid(field("shapes"), "must contain at least one shape")),
+                    1 => children.pop().unwrap(),
+                    _ => Arc::new(BvhNode::new(&children, self.time0, self.time1)),
+                };
+                for (step_index, step) in transform.iter().enumerate() {
+                    let step_field = field(&format!("transform[{}]", step_index));
+                    // Building the matrix validates the step
+                    let matrix = self.transform_matrix(step, &step_field)?;
+                    child = match step {
+                        TransformStep::Translate(offset) => Translate::arc(child, (*offset).into()),
+                        TransformStep::RotateX(degrees) => RotateX::arc(child, *degrees),
+                        TransformStep::RotateY(degrees) => RotateY::arc(child, *degrees),
+                        TransformStep::RotateZ(degrees) => RotateZ::arc(child, *degrees),
+                        TransformStep::Scale(factors) => Scale::arc(child, (*factors).into()),
+                        TransformStep::Matrix(_) => Transform::arc(child, matrix),
+                    };
+                }
+                list.push(child);
+            }
+        }
+        if let (Some(lights), Some(material)) = (lights, shape.light_material()) {
+            if self.emissive_materials.contains(material) {
+                lights.push(list.last().unwrap().clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn transform_matrix(&self, step: &TransformStep, field: &str) -> Result<Matrix4, SceneError> {
+        let invalid = |message: &str| SceneError::Invalid {
+            path: self.path.to_path_buf(),
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+        let matrix = match step {
+            TransformStep::Translate(offset) => Matrix4::translation((*offset).into()),
+            TransformStep::RotateX(degrees) => Matrix4::rotation(0, *degrees),
+            TransformStep::RotateY(degrees) => Matrix4::rotation(1, *degrees),
+            TransformStep::RotateZ(degrees) => Matrix4::rotation(2, *degrees),
+            TransformStep::Scale(factors) => {
+                let factors: Vec3 = (*factors).into();
+                if factors.x() == 0.0 || factors.y() == 0.0 || factors.z() == 0.0 {
+                    return Err(invalid("scale factors must not be zero"));
+                }
+                Matrix4::scale(factors)
+            }
+            TransformStep::Matrix(rows) => {
+                let matrix = Matrix4::new(*rows);
+                if !matrix.is_affine() {
+                    return Err(invalid("the bottom row must be [0, 0, 0, 1]"));
+                }
+                if matrix.inverse().is_none() {
+                    return Err(invalid("must be invertible"));
+                }
+                matrix
+            }
+        };
+        Ok(matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{parse_scene, ImageOverrides, Scene, SceneError};
+    use crate::{
+        hitable::Hitable,
+        structures::{Ray, Vec3},
+    };
+
+    const WHITE: &str = r#""white": { "type": "diffuse", "albedo": [0.5, 0.5, 0.5] }"#;
+    const BALL: &str =
+        r#""ball": [{ "type": "sphere", "centre": [0, 0, 0], "radius": 1, "material": "white" }]"#;
+    const SPHERE: &str =
+        r#"{ "type": "sphere", "centre": [0, 0, 0], "radius": 1, "material": "white" }"#;
+
+    // A scene with a sphere material and an object holding a unit sphere, with `shapes`
+    // spliced into its shape list
+    fn load(shapes: &str) -> Result<Scene, SceneError> {
+        load_with(WHITE, BALL, shapes)
+    }
+
+    // As load, with `materials` and `objects` spliced into those maps in place of the
+    // defaults
+    fn load_with(materials: &str, objects: &str, shapes: &str) -> Result<Scene, SceneError> {
+        let source = format!(
+            r#"{{
+                "image": {{ "width": 4, "height": 4, "samples": 1 }},
+                "camera": {{ "position": [0, 0, 5], "look_at": [0, 0, 0], "vertical_fov": 40 }},
+                "materials": {{ {} }},
+                "objects": {{ {} }},
+                "shapes": [{}]
+            }}"#,
+            materials, objects, shapes
+        );
+        parse_scene(
+            Path::new("test.json"),
+            &source,
+            &ImageOverrides::default(),
+            |_| {},
+        )
+    }
+
+    fn assert_invalid(result: Result<Scene, SceneError>, expected_field: &str, expected: &str) {
+        match result {
+            Err(SceneError::Invalid { field, message, .. }) => {
+                assert_eq!(field, expected_field);
+                assert_eq!(message, expected);
+            }
+            Err(error) => panic!("expected an invalid {}, got {}", expected_field, error),
+            Ok(_) => panic!(
+                "expected an invalid {}, but the scene loaded",
+                expected_field
+            ),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        assert_invalid(
+            load(r#"{ "type": "sphere", "centre": [0, 0, 0], "radius": 1, "material": "chrome" }"#),
+            "shapes[0].material",
+            "unknown material 'chrome'",
+        );
+        assert_invalid(
+            load_with(
+                &format!(
+                    r#"{}, "checked": {{ "type": "diffuse", "albedo": "stripes" }}"#,
+                    WHITE
+                ),
+                BALL,
+                SPHERE,
+            ),
+            "materials.checked.albedo",
+            "unknown texture 'stripes'",
+        );
+        assert_invalid(
+            load(r#"{ "type": "instance", "object": "crate" }"#),
+            "shapes[0].object",
+            "unknown object 'crate'",
+        );
+    }
+
+    #[test]
+    fn rejects_empty_sizes() {
+        assert_invalid(
+            load(r#"{ "type": "sphere", "centre": [0, 0, 0], "radius": 0, "material": "white" }"#),
+            "shapes[0].radius",
+            "must be greater than zero",
+        );
+        assert_invalid(
+            load(&format!(
+                r#"{{ "type": "constant_medium", "boundary": {}, "density": 0, "material": "white" }}"#,
+                SPHERE
+            )),
+            "shapes[0].density",
+            "must be greater than zero",
+        );
+        // The density is checked before the grid is read, so the grid needn't exist
+        assert_invalid(
+            load(
+                r#"{ "type": "voxel_volume", "path": "missing.vgrid", "min": [0, 0, 0],
+                     "max": [1, 1, 1], "density": 0 }"#,
+            ),
+            "shapes[0].density",
+            "must be greater than zero",
+        );
+    }
+
+    #[test]
+    fn rejects_zero_scales() {
+        assert_invalid(
+            load(&format!(
+                r#"{{ "type": "transform", "transform": [{{ "translate": [1, 0, 0] }}, {{ "scale": [1, 0, 1] }}],
+                     "shapes": [{}] }}"#,
+                SPHERE
+            )),
+            "shapes[0].transform[1]",
+            "scale factors must not be zero",
+        );
+        assert_invalid(
+            load(r#"{ "type": "instance", "object": "ball", "transform": [{ "scale": 0 }] }"#),
+            "shapes[0].transform[0]",
+            "scale factors must not be zero",
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_matrices() {
+        assert_invalid(
+            load(&format!(
+                r#"{{ "type": "transform", "transform": [{{ "matrix": [
+                     [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 1, 1]
+                 ] }}], "shapes": [{}] }}"#,
+                SPHERE
+            )),
+            "shapes[0].transform[0]",
+            "the bottom row must be [0, 0, 0, 1]",
+        );
+        assert_invalid(
+            load(
+                r#"{ "type": "instance", "object": "ball", "transform": [{ "matrix": [
+                     [1, 0, 0, 0], [0, 1, 0, 0], [1, 1, 0, 0], [0, 0, 0, 1]
+                 ] }] }"#,
+            ),
+            "shapes[0].transform[0]",
+            "must be invertible",
+        );
+    }
+
+    #[test]
+    fn rejects_nested_instances() {
+        let instance = r#"{ "type": "instance", "object": "ball" }"#;
+        assert_invalid(
+            load_with(
+                WHITE,
+                &format!(r#"{}, "pair": [{}]"#, BALL, instance),
+                SPHERE,
+            ),
+            "objects.pair[0].type",
+            "instances can only be placed in the scene's shapes",
+        );
+        // Transforms pass on whether instances are allowed to the shapes inside them
+        let transformed = format!(
+            r#"{{ "type": "transform", "transform": [], "shapes": [{}, {}] }}"#,
+            SPHERE, instance
+        );
+        assert!(load(&transformed).is_ok());
+        assert_invalid(
+            load_with(WHITE, &format!(r#""pair": [{}]"#, transformed), SPHERE),
+            "objects.pair[0].shapes[1].type",
+            "instances can only be placed in the scene's shapes",
+        );
+    }
+
+    #[test]
+    fn locates_parse_errors() {
+        let source = r#"{
+"image": { "width": 4, "height": "tall", "samples": 1 },
+"camera": { "position": [0, 0, 5], "look_at": [0, 0, 0], "vertical_fov": 40 },
+"materials": {},
+"shapes": []
+}"#;
+        let result = parse_scene(
+            Path::new("test.json"),
+            source,
+            &ImageOverrides::default(),
+            |_| {},
+        );
+        match result {
+            Err(SceneError::Parse {
+                path,
+                field,
+                line,
+                column,
+                message,
+            }) => {
+                assert_eq!(path, Path::new("test.json"));
+                assert_eq!(field, "image.height");
+                // Just past the value in error
+                assert_eq!((line, column), (2, 39));
+                // serde_json's own copy of the location is left off the message
+                assert_eq!(message, "invalid type: string \"tall\", expected usize");
+            }
+            Err(error) => panic!("expected a parse error, got {}", error),
+            Ok(_) => panic!("expected a parse error, but the scene loaded"),
+        }
+    }
+
+    #[test]
+    fn loads_tiny_instances() {
+        let scene = load(
+            r#"{ "type": "instance", "object": "ball", "transform": [
+                { "scale": 0.01 }, { "scale": 0.01 }, { "scale": 0.01 }, { "translate": [0, 0, 1] }
+            ] }"#,
+        )
+        .unwrap();
+        // The ball has a radius of a millionth, so is looked at from just outside it
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 1.00001), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.0, f32::MAX).unwrap();
+        assert!(
+            (hit.distance - 9e-6).abs() < 5e-7,
+            "hit at {}",
+            hit.distance
+        );
+    }
+}
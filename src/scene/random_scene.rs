@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    camera::Camera,
+    environments::Gradient,
+    hitable::Hitable,
+    materials::{Dielectric, Diffuse, Metal},
+    random,
+    shapes::Sphere,
+    structures::{BvhNode, Vec3},
+    textures::{CheckerTexture, ConstantTexture},
+};
+
+use super::Scene;
+
+/// The final scene from the first book: a field of small random spheres around three large ones.
+pub fn random_scene(width: usize, height: usize, samples: usize) -> Scene {
+    let camera = Camera::new(
+        Vec3::new(13.0, 2.0, 3.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        20.0,
+        width as f32 / height as f32,
+        0.0,
+        10.0,
+        0.0,
+        0.0,
+    );
+    Scene {
+        world: random_world(),
+        camera,
+        width,
+        height,
+        samples,
+        environment: Gradient::sky(),
+        lights: Vec::new(),
+    }
+}
+
+fn random_world() -> BvhNode {
+    let n = 500;
+    let mut rng = random::rng();
+    let mut list = Vec::<Arc<dyn Hitable>>::with_capacity(n + 1);
+    let checker = CheckerTexture::arc(
+        0.32,
+        ConstantTexture::arc(Vec3::new(0.2, 0.3, 0.1)),
+        ConstantTexture::arc(Vec3::new(0.9, 0.9, 0.9)),
+    );
+    list.push(Sphere::arc(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Diffuse::textured_arc(checker),
+    ));
+    let headliners_plane = Vec3::new(4.0, 0.2, 0.0);
+    for a in -11..11 {
+        for b in -11..11 {
+            let mat_choice = rng.gen::<f32>();
+            let centre = Vec3::new(
+                a as f32 + 0.9 + rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.9 + rng.gen::<f32>(),
+            );
+            if (centre - headliners_plane).length() > 0.9 {
+                let mat = if mat_choice < 0.8 {
+                    Diffuse::arc(Vec3::new(
+                        rng.gen::<f32>() * rng.gen::<f32>(),
+                        rng.gen::<f32>() * rng.gen::<f32>(),
+                        rng.gen::<f32>() * rng.gen::<f32>(),
+                    ))
+                } else if mat_choice < 0.95 {
+                    Metal::arc(
+                        Vec3::new(
+                            rng.gen::<f32>() * rng.gen::<f32>(),
+                            rng.gen::<f32>() * rng.gen::<f32>(),
+                            rng.gen::<f32>() * rng.gen::<f32>(),
+                        ),
+                        0.5 * rng.gen::<f32>(),
+                    )
+                } else {
+                    Dielectric::arc(1.5)
+                };
+                list.push(Sphere::arc(centre, 0.2, mat));
+            }
+        }
+    }
+
+    list.push(Sphere::arc(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Dielectric::arc(1.5),
+    ));
+
+    list.push(Sphere::arc(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Diffuse::arc(Vec3::new(0.8, 0.8, 0.8)),
+    ));
+
+    list.push(Sphere::arc(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        Metal::arc(Vec3::new(0.7, 0.6, 0.5), 0.0),
+    ));
+
+    BvhNode::new(&list[..], 0.0, 0.0)
+}
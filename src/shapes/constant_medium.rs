@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    material::Material,
+    random,
+    structures::{Ray, Vec3, AABB},
+};
+
+// Keeps the search for the exit point from finding the entry point again
+const EXIT_EPSILON: f32 = 1e-4;
+
+/// A volume of uniform density filling a closed boundary, such as smoke or fog. A ray
+/// passing through it scatters after an exponentially distributed distance, or passes
+/// straight through.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hitable>,
+    density: f32,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// The boundary must be closed, and is only used to find where rays enter and leave the
+    /// volume, so its own material is ignored.
+    pub fn new(
+        boundary: Arc<dyn Hitable>,
+        density: f32,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            density > 0.0,
+            "A medium's density must be greater than zero"
+        );
+        Self {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+
+    pub fn arc(
+        boundary: Arc<dyn Hitable>,
+        density: f32,
+        phase_function: Arc<dyn Material>,
+    ) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(boundary, density, phase_function))
+    }
+
+    // The part of the ray between t_min and t_max which lies inside the boundary
+    fn span(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        // Searching the whole line finds the entry point even when the ray starts inside
+        let entry = self.boundary.hit(ray, -f32::MAX, f32::MAX)?;
+        let exit = self
+            .boundary
+            .hit(ray, entry.distance + EXIT_EPSILON, f32::MAX)?;
+        let start = entry.distance.max(t_min).max(0.0);
+        let end = exit.distance.min(t_max);
+        (start < end).then_some((start, end))
+    }
+}
+
+impl Hitable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let (start, end) = self.span(ray, t_min, t_max)?;
+        let ray_length = ray.direction.length();
+        let distance_inside = (end - start) * ray_length;
+        let hit_distance = -random::rng().gen::<f32>().ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+        let distance = start + hit_distance / ray_length;
+        Some(RayHit {
+            distance,
+            point: ray.point_at(distance),
+            // Scattering in a medium does not depend on the normal, so any will do
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            u: 0.0,
+            v: 0.0,
+            material: self.phase_function.clone(),
+        })
+    }
+
+    // The fraction of light which is not scattered over the length of the ray inside the
+    // medium follows directly from the density
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        match self.span(ray, t_min, t_max) {
+            Some((start, end)) => (-self.density * (end - start) * ray.direction.length()).exp(),
+            None => 1.0,
+        }
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.boundary.bounding_box(t0, t1)
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    material::Material,
+    structures::{Ray, Vec3, AABB},
+};
+
+use super::Quad;
+
+const BOX_PADDING: f32 = 1e-4;
+
+/// An axis aligned box made of six quads. Unlike a lone quad its normals always point
+/// out of the box, so it can bound glass or a volume.
+pub struct Cuboid {
+    faces: [Quad; 6],
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Cuboid {
+    /// Takes any two opposite corners.
+    pub fn new(a: Vec3, b: Vec3, material: Arc<dyn Material>) -> Self {
+        let min = Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+        let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+        // Each pair of edges is ordered so their cross product points out of the box
+        let faces = [
+            Quad::new(
+                Vec3::new(min.x(), min.y(), max.z()),
+                dx,
+                dy,
+                material.clone(),
+            ),
+            Quad::new(
+                Vec3::new(max.x(), min.y(), min.z()),
+                -dx,
+                dy,
+                material.clone(),
+            ),
+            Quad::new(
+                Vec3::new(max.x(), min.y(), max.z()),
+                -dz,
+                dy,
+                material.clone(),
+            ),
+            Quad::new(min, dz, dy, material.clone()),
+            Quad::new(
+                Vec3::new(min.x(), max.y(), max.z()),
+                dx,
+                -dz,
+                material.clone(),
+            ),
+            Quad::new(min, dx, dz, material),
+        ];
+        Self { faces, min, max }
+    }
+
+    pub fn arc(a: Vec3, b: Vec3, material: Arc<dyn Material>) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(a, b, material))
+    }
+}
+
+impl Hitable for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let mut closest_so_far = t_max;
+        let mut best_hit: Option<(RayHit, Vec3)> = None;
+        for face in &self.faces {
+            if let Some(hit) = face.hit(ray, t_min, closest_so_far) {
+                closest_so_far = hit.distance;
+                best_hit = Some((hit, face.normal()));
+            }
+        }
+        best_hit.map(|(hit, outward_normal)| RayHit {
+            normal: outward_normal,
+            ..hit
+        })
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let padding = Vec3::new(BOX_PADDING, BOX_PADDING, BOX_PADDING);
+        Some(AABB::new(self.min - padding, self.max + padding))
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    structures::{BvhNode, Matrix4, Ray, AABB},
+};
+
+use super::Transform;
+
+/// Places a shared object in the world with an affine transform, so one BVH can be
+/// referenced many times without copying its geometry. Instances are usually gathered
+/// into a top level [`BvhNode`] of their own.
+pub struct Instance {
+    transform: Transform,
+    bounding_box: AABB,
+}
+
+impl Instance {
+    /// Panics if the transform is not an invertible affine matrix.
+    pub fn new(object: Arc<BvhNode>, object_to_world: Matrix4) -> Self {
+        let transform = Transform::new(object, object_to_world);
+        // The object's BVH was built for its own time range, so any times give its box
+        let bounding_box = transform
+            .bounding_box(0.0, 0.0)
+            .expect("A BVH always has a bounding box");
+        Self {
+            transform,
+            bounding_box,
+        }
+    }
+
+    pub fn arc(object: Arc<BvhNode>, object_to_world: Matrix4) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(object, object_to_world))
+    }
+}
+
+impl Hitable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        self.transform.hit(ray, t_min, t_max)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        self.transform.hit_cost(ray, t_min, t_max)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.transform.transmittance(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.bounding_box)
+    }
+}
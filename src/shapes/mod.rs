@@ -1,5 +1,29 @@
+mod constant_medium;
+mod cuboid;
+mod instance;
 mod moving_sphere;
+mod quad;
+mod rect;
+mod rotate;
+mod scale;
 mod sphere;
+mod transform;
+mod translate;
+mod triangle;
+mod triangle_mesh;
+mod voxel_volume;
 
+pub use constant_medium::*;
+pub use cuboid::*;
+pub use instance::*;
 pub use moving_sphere::*;
+pub use quad::*;
+pub use rect::*;
+pub use rotate::*;
+pub use scale::*;
 pub use sphere::*;
+pub use transform::*;
+pub use translate::*;
+pub use triangle::*;
+pub use triangle_mesh::*;
+pub use voxel_volume::*;
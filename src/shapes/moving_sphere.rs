@@ -6,6 +6,8 @@ use crate::{
     structures::{Ray, Vec3, AABB},
 };
 
+use super::sphere::sphere_uv;
+
 pub struct MovingSphere {
     centre0: Vec3,
     centre1: Vec3,
@@ -61,19 +63,27 @@ impl Hitable for MovingSphere {
         if descriminant > 0.0 {
             let distance = (-b - (b.powi(2) - a * c).sqrt()) / a;
             if distance < t_max && distance > t_min {
+                let normal = (ray.point_at(distance) - self.centre(ray.time)) / self.radius;
+                let (u, v) = sphere_uv(&normal);
                 return Some(RayHit {
                     distance,
                     point: ray.point_at(distance),
-                    normal: (ray.point_at(distance) - self.centre(ray.time)) / self.radius,
+                    normal,
+                    u,
+                    v,
                     material: self.material.clone(),
                 });
             }
             let distance = (-b + (b.powi(2) - a * c).sqrt()) / a;
             if distance < t_max && distance > t_min {
+                let normal = (ray.point_at(distance) - self.centre(ray.time)) / self.radius;
+                let (u, v) = sphere_uv(&normal);
                 return Some(RayHit {
                     distance,
                     point: ray.point_at(distance),
-                    normal: (ray.point_at(distance) - self.centre(ray.time)) / self.radius,
+                    normal,
+                    u,
+                    v,
                     material: self.material.clone(),
                 });
             }
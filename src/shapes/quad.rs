@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    hitable::{solid_angle_pdf, Hitable, RayHit, SurfaceSample},
+    material::Material,
+    random,
+    structures::{Ray, Vec3, AABB},
+};
+
+const QUAD_EPSILON: f32 = 1e-8;
+const BOX_PADDING: f32 = 1e-4;
+
+/// A parallelogram with a corner at the origin and sides along the two edges. It is
+/// two sided, so the normal always faces the incoming ray.
+pub struct Quad {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    // Projects a point on the plane onto the edges, giving its UVs
+    w: Vec3,
+    area: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn new(origin: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(&v);
+        Self {
+            origin,
+            u,
+            v,
+            normal: n.unit(),
+            w: n / n.dot(&n),
+            area: n.length(),
+            material,
+        }
+    }
+
+    pub fn arc(origin: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(origin, u, v, material))
+    }
+
+    /// The normal on the side of the edges' cross product.
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+impl Hitable for Quad {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let denominator = self.normal.dot(&ray.direction);
+        if denominator.abs() < QUAD_EPSILON {
+            return None;
+        }
+        let distance = (self.origin - ray.origin).dot(&self.normal) / denominator;
+        if distance <= t_min || distance >= t_max {
+            return None;
+        }
+        let point = ray.point_at(distance);
+        let planar = point - self.origin;
+        let u = self.w.dot(&planar.cross(&self.v));
+        let v = self.w.dot(&self.u.cross(&planar));
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some(RayHit {
+            distance,
+            point,
+            normal: if denominator < 0.0 {
+                self.normal
+            } else {
+                -self.normal
+            },
+            u,
+            v,
+            material: self.material.clone(),
+        })
+    }
+
+    fn sample_direction(&self, origin: &Vec3) -> Option<Vec3> {
+        self.sample_surface()
+            .map(|sample| sample.hit.point - origin)
+    }
+
+    fn direction_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |hit| solid_angle_pdf(ray, &hit, self.area))
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let mut rng = random::rng();
+        let (u, v) = (rng.gen::<f32>(), rng.gen::<f32>());
+        Some(SurfaceSample {
+            hit: RayHit {
+                distance: 0.0,
+                point: self.origin + u * self.u + v * self.v,
+                normal: self.normal,
+                u,
+                v,
+                material: self.material.clone(),
+            },
+            pdf: 1.0 / self.area,
+        })
+    }
+
+    fn surface_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |_| 1.0 / self.area)
+    }
+
+    // Padded so quads lying in an axis plane still have a box with some volume
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let corners = [
+            self.origin,
+            self.origin + self.u,
+            self.origin + self.v,
+            self.origin + self.u + self.v,
+        ];
+        let padding = Vec3::new(BOX_PADDING, BOX_PADDING, BOX_PADDING);
+        let bounding_box = corners[1..]
+            .iter()
+            .fold(AABB::new(corners[0], corners[0]), |acc, corner| {
+                AABB::surrounding_box(&acc, &AABB::new(*corner, *corner))
+            });
+        Some(AABB::new(
+            bounding_box.min - padding,
+            bounding_box.max + padding,
+        ))
+    }
+}
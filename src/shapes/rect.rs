@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    hitable::{solid_angle_pdf, Hitable, RayHit, SurfaceSample},
+    material::Material,
+    random,
+    structures::{Ray, Vec3, AABB},
+};
+
+const BOX_PADDING: f32 = 1e-4;
+
+/// A rectangle in the plane z = k, spanning x0..x1 and y0..y1.
+pub type XyRect = Rect<2>;
+/// A rectangle in the plane y = k, spanning x0..x1 and z0..z1.
+pub type XzRect = Rect<1>;
+/// A rectangle in the plane x = k, spanning y0..y1 and z0..z1.
+pub type YzRect = Rect<0>;
+
+/// An axis aligned rectangle perpendicular to the `AXIS` axis. It is two sided, so the
+/// normal always faces the incoming ray.
+pub struct Rect<const AXIS: usize> {
+    // The bounds along the first and second of the other two axes, in x, y, z order
+    a: (f32, f32),
+    b: (f32, f32),
+    k: f32,
+    material: Arc<dyn Material>,
+}
+
+impl<const AXIS: usize> Rect<AXIS> {
+    const A: usize = if AXIS == 0 { 1 } else { 0 };
+    const B: usize = if AXIS == 2 { 1 } else { 2 };
+
+    pub fn new(a0: f32, a1: f32, b0: f32, b1: f32, k: f32, material: Arc<dyn Material>) -> Self {
+        Self {
+            a: (a0.min(a1), a0.max(a1)),
+            b: (b0.min(b1), b0.max(b1)),
+            k,
+            material,
+        }
+    }
+
+    pub fn arc(
+        a0: f32,
+        a1: f32,
+        b0: f32,
+        b1: f32,
+        k: f32,
+        material: Arc<dyn Material>,
+    ) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(a0, a1, b0, b1, k, material))
+    }
+
+    fn area(&self) -> f32 {
+        (self.a.1 - self.a.0) * (self.b.1 - self.b.0)
+    }
+}
+
+impl<const AXIS: usize> Hitable for Rect<AXIS> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let distance = (self.k - ray.origin[AXIS]) / ray.direction[AXIS];
+        // Also rejects rays parallel to the plane, which give infinite or NaN distances
+        if !(distance > t_min && distance < t_max) {
+            return None;
+        }
+        let point = ray.point_at(distance);
+        let (a, b) = (point[Self::A], point[Self::B]);
+        if a < self.a.0 || a > self.a.1 || b < self.b.0 || b > self.b.1 {
+            return None;
+        }
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        normal[AXIS] = if ray.direction[AXIS] < 0.0 { 1.0 } else { -1.0 };
+        Some(RayHit {
+            distance,
+            point,
+            normal,
+            u: (a - self.a.0) / (self.a.1 - self.a.0),
+            v: (b - self.b.0) / (self.b.1 - self.b.0),
+            material: self.material.clone(),
+        })
+    }
+
+    fn sample_direction(&self, origin: &Vec3) -> Option<Vec3> {
+        self.sample_surface()
+            .map(|sample| sample.hit.point - origin)
+    }
+
+    fn direction_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |hit| solid_angle_pdf(ray, &hit, self.area()))
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let mut rng = random::rng();
+        let (u, v) = (rng.gen::<f32>(), rng.gen::<f32>());
+        let mut point = Vec3::new(0.0, 0.0, 0.0);
+        point[Self::A] = self.a.0 + u * (self.a.1 - self.a.0);
+        point[Self::B] = self.b.0 + v * (self.b.1 - self.b.0);
+        point[AXIS] = self.k;
+        let mut normal = Vec3::new(0.0, 0.0, 0.0);
+        normal[AXIS] = 1.0;
+        Some(SurfaceSample {
+            hit: RayHit {
+                distance: 0.0,
+                point,
+                normal,
+                u,
+                v,
+                material: self.material.clone(),
+            },
+            pdf: 1.0 / self.area(),
+        })
+    }
+
+    fn surface_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |_| 1.0 / self.area())
+    }
+
+    // Padded so the box has some thickness along the rectangle's normal
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let mut min = Vec3::new(0.0, 0.0, 0.0);
+        let mut max = Vec3::new(0.0, 0.0, 0.0);
+        min[Self::A] = self.a.0;
+        max[Self::A] = self.a.1;
+        min[Self::B] = self.b.0;
+        max[Self::B] = self.b.1;
+        min[AXIS] = self.k - BOX_PADDING;
+        max[AXIS] = self.k + BOX_PADDING;
+        Some(AABB::new(min, max))
+    }
+}
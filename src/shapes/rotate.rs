@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    structures::{Matrix4, Ray, Vec3, AABB},
+};
+
+/// Rotation about the x axis, counterclockwise when looking back down the axis.
+pub type RotateX = Rotate<0>;
+/// Rotation about the y axis, counterclockwise when looking back down the axis.
+pub type RotateY = Rotate<1>;
+/// Rotation about the z axis, counterclockwise when looking back down the axis.
+pub type RotateZ = Rotate<2>;
+
+pub struct Rotate<const AXIS: usize> {
+    child: Arc<dyn Hitable>,
+    sin_theta: f32,
+    cos_theta: f32,
+    matrix: Matrix4,
+}
+
+impl<const AXIS: usize> Rotate<AXIS> {
+    pub fn new(child: Arc<dyn Hitable>, degrees: f32) -> Self {
+        let radians = degrees.to_radians();
+        Self {
+            child,
+            sin_theta: radians.sin(),
+            cos_theta: radians.cos(),
+            matrix: Matrix4::rotation(AXIS, degrees),
+        }
+    }
+
+    pub fn arc(child: Arc<dyn Hitable>, degrees: f32) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(child, degrees))
+    }
+
+    // Rotates the two components perpendicular to the axis, backwards if sin_theta is negated
+    fn rotate(&self, vector: &Vec3, sin_theta: f32) -> Vec3 {
+        let (i, j) = ((AXIS + 1) % 3, (AXIS + 2) % 3);
+        let mut rotated = *vector;
+        rotated[i] = self.cos_theta * vector[i] - sin_theta * vector[j];
+        rotated[j] = sin_theta * vector[i] + self.cos_theta * vector[j];
+        rotated
+    }
+
+    fn rotated_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.rotate(&ray.origin, -self.sin_theta),
+            self.rotate(&ray.direction, -self.sin_theta),
+            ray.time,
+        )
+    }
+}
+
+impl<const AXIS: usize> Hitable for Rotate<AXIS> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let mut hit = self.child.hit(&self.rotated_ray(ray), t_min, t_max)?;
+        hit.point = self.rotate(&hit.point, self.sin_theta);
+        hit.normal = self.rotate(&hit.normal, self.sin_theta);
+        Some(hit)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        self.child.hit_cost(&self.rotated_ray(ray), t_min, t_max)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.child
+            .transmittance(&self.rotated_ray(ray), t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.child
+            .bounding_box(t0, t1)
+            .map(|bounding_box| self.matrix.transform_box(&bounding_box))
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    structures::{Ray, Vec3, AABB},
+};
+
+/// Scales along each axis about the origin. Negative factors mirror the child.
+pub struct Scale {
+    child: Arc<dyn Hitable>,
+    factors: Vec3,
+}
+
+impl Scale {
+    /// Panics if any factor is zero.
+    pub fn new(child: Arc<dyn Hitable>, factors: Vec3) -> Self {
+        assert!(
+            factors.x() != 0.0 && factors.y() != 0.0 && factors.z() != 0.0,
+            "Scale factors must not be zero"
+        );
+        Self { child, factors }
+    }
+
+    pub fn arc(child: Arc<dyn Hitable>, factors: Vec3) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(child, factors))
+    }
+
+    pub fn uniform_arc(child: Arc<dyn Hitable>, factor: f32) -> Arc<dyn Hitable> {
+        Self::arc(child, Vec3::new(factor, factor, factor))
+    }
+
+    // The direction is left unnormalised so distances along it match those outside
+    fn scaled_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            ray.origin / self.factors,
+            ray.direction / self.factors,
+            ray.time,
+        )
+    }
+}
+
+impl Hitable for Scale {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let mut hit = self.child.hit(&self.scaled_ray(ray), t_min, t_max)?;
+        hit.point = ray.point_at(hit.distance);
+        hit.normal = (hit.normal / self.factors).unit();
+        Some(hit)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        self.child.hit_cost(&self.scaled_ray(ray), t_min, t_max)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.child
+            .transmittance(&self.scaled_ray(ray), t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.child.bounding_box(t0, t1).map(|bounding_box| {
+            let (a, b) = (
+                bounding_box.min * self.factors,
+                bounding_box.max * self.factors,
+            );
+            AABB::new(
+                Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+                Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+            )
+        })
+    }
+}
@@ -1,8 +1,11 @@
-use std::sync::Arc;
+use std::{f32::consts, sync::Arc};
+
+use rand::Rng;
 
 use crate::{
-    hitable::{Hitable, RayHit},
+    hitable::{Hitable, RayHit, SurfaceSample},
     material::Material,
+    random,
     structures::{Ray, Vec3, AABB},
 };
 
@@ -24,6 +27,15 @@ impl Sphere {
     pub fn arc(centre: Vec3, radius: f32, material: Arc<dyn Material>) -> Arc<dyn Hitable> {
         Arc::new(Self::new(centre, radius, material))
     }
+
+    // Cosine of the half angle of the cone the sphere fills, as seen from outside it
+    fn cone_cos_theta_max(&self, origin: &Vec3) -> Option<f32> {
+        let distance_squared = (self.centre - origin).length_squared();
+        if distance_squared <= self.radius.powi(2) {
+            return None;
+        }
+        Some((1.0 - self.radius.powi(2) / distance_squared).sqrt())
+    }
 }
 
 impl Hitable for Sphere {
@@ -36,19 +48,27 @@ impl Hitable for Sphere {
         if descriminant > 0.0 {
             let distance = (-b - (b.powi(2) - a * c).sqrt()) / a;
             if distance < t_max && distance > t_min {
+                let normal = (ray.point_at(distance) - self.centre) / self.radius;
+                let (u, v) = sphere_uv(&normal);
                 return Some(RayHit {
                     distance,
                     point: ray.point_at(distance),
-                    normal: (ray.point_at(distance) - self.centre) / self.radius,
+                    normal,
+                    u,
+                    v,
                     material: self.material.clone(),
                 });
             }
             let distance = (-b + (b.powi(2) - a * c).sqrt()) / a;
             if distance < t_max && distance > t_min {
+                let normal = (ray.point_at(distance) - self.centre) / self.radius;
+                let (u, v) = sphere_uv(&normal);
                 return Some(RayHit {
                     distance,
                     point: ray.point_at(distance),
-                    normal: (ray.point_at(distance) - self.centre) / self.radius,
+                    normal,
+                    u,
+                    v,
                     material: self.material.clone(),
                 });
             }
@@ -56,6 +76,55 @@ impl Hitable for Sphere {
         None
     }
 
+    // Only the cone of directions which can reach the sphere is sampled, uniformly
+    fn sample_direction(&self, origin: &Vec3) -> Option<Vec3> {
+        let cos_theta_max = self.cone_cos_theta_max(origin)?;
+        let mut rng = random::rng();
+        let cos_theta = 1.0 - rng.gen::<f32>() * (1.0 - cos_theta_max);
+        let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+        let phi = 2.0 * consts::PI * rng.gen::<f32>();
+
+        let w = (self.centre - origin).unit();
+        let helper = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&helper).unit();
+        let u = w.cross(&v);
+        Some(sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w)
+    }
+
+    fn direction_pdf(&self, ray: &Ray) -> f32 {
+        match self.cone_cos_theta_max(&ray.origin) {
+            Some(cos_theta_max) if self.hit(ray, 0.0001, f32::MAX).is_some() => {
+                1.0 / (2.0 * consts::PI * (1.0 - cos_theta_max))
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let normal = Vec3::get_point_on_unit_sphere();
+        let (u, v) = sphere_uv(&normal);
+        Some(SurfaceSample {
+            hit: RayHit {
+                distance: 0.0,
+                point: self.centre + self.radius * normal,
+                normal,
+                u,
+                v,
+                material: self.material.clone(),
+            },
+            pdf: 1.0 / (4.0 * consts::PI * self.radius.powi(2)),
+        })
+    }
+
+    fn surface_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |_| 1.0 / (4.0 * consts::PI * self.radius.powi(2)))
+    }
+
     fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
         Some(AABB::new(
             self.centre - Vec3::new(self.radius, self.radius, self.radius),
@@ -63,3 +132,11 @@ impl Hitable for Sphere {
         ))
     }
 }
+
+/// Maps a point on the unit sphere to UVs, with u running around the equator from -x and
+/// v from the bottom pole to the top.
+pub(crate) fn sphere_uv(point: &Vec3) -> (f32, f32) {
+    let theta = f32::acos(-point.y().clamp(-1.0, 1.0));
+    let phi = f32::atan2(-point.z(), point.x()) + consts::PI;
+    (phi / (2.0 * consts::PI), theta / consts::PI)
+}
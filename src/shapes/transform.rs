@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    structures::{Matrix4, Ray, AABB},
+};
+
+/// Applies a general affine transform to its child.
+pub struct Transform {
+    child: Arc<dyn Hitable>,
+    object_to_world: Matrix4,
+    world_to_object: Matrix4,
+    // Normals are transformed by the inverse transpose to stay perpendicular to the surface
+    normal_to_world: Matrix4,
+}
+
+impl Transform {
+    /// Panics if the transform is not an invertible affine matrix.
+    pub fn new(child: Arc<dyn Hitable>, object_to_world: Matrix4) -> Self {
+        assert!(object_to_world.is_affine(), "A transform must be affine");
+        let world_to_object = object_to_world
+            .inverse()
+            .expect("A transform must be invertible");
+        Self {
+            child,
+            object_to_world,
+            world_to_object,
+            normal_to_world: world_to_object.transpose(),
+        }
+    }
+
+    pub fn arc(child: Arc<dyn Hitable>, object_to_world: Matrix4) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(child, object_to_world))
+    }
+
+    // The direction is left unnormalised so distances along it match those in world space
+    fn object_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.world_to_object.transform_point(&ray.origin),
+            self.world_to_object.transform_vector(&ray.direction),
+            ray.time,
+        )
+    }
+}
+
+impl Hitable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let mut hit = self.child.hit(&self.object_ray(ray), t_min, t_max)?;
+        hit.point = ray.point_at(hit.distance);
+        hit.normal = self.normal_to_world.transform_vector(&hit.normal).unit();
+        Some(hit)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        self.child.hit_cost(&self.object_ray(ray), t_min, t_max)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.child
+            .transmittance(&self.object_ray(ray), t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.child
+            .bounding_box(t0, t1)
+            .map(|bounding_box| self.object_to_world.transform_box(&bounding_box))
+    }
+}
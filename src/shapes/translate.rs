@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    structures::{Ray, Vec3, AABB},
+};
+
+pub struct Translate {
+    child: Arc<dyn Hitable>,
+    offset: Vec3,
+}
+
+impl Translate {
+    pub fn new(child: Arc<dyn Hitable>, offset: Vec3) -> Self {
+        Self { child, offset }
+    }
+
+    pub fn arc(child: Arc<dyn Hitable>, offset: Vec3) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(child, offset))
+    }
+
+    fn moved_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(ray.origin - self.offset, ray.direction, ray.time)
+    }
+}
+
+impl Hitable for Translate {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let mut hit = self.child.hit(&self.moved_ray(ray), t_min, t_max)?;
+        hit.point += self.offset;
+        Some(hit)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        self.child.hit_cost(&self.moved_ray(ray), t_min, t_max)
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        self.child.transmittance(&self.moved_ray(ray), t_min, t_max)
+    }
+
+    fn bounding_box(&self, t0: f32, t1: f32) -> Option<AABB> {
+        self.child.bounding_box(t0, t1).map(|bounding_box| {
+            AABB::new(
+                bounding_box.min + self.offset,
+                bounding_box.max + self.offset,
+            )
+        })
+    }
+}
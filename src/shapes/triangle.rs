@@ -0,0 +1,194 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    hitable::{solid_angle_pdf, Hitable, RayHit, SurfaceSample},
+    material::Material,
+    random,
+    structures::{Ray, Vec3, AABB},
+};
+
+const TRIANGLE_EPSILON: f32 = 1e-8;
+const BOX_PADDING: f32 = 1e-4;
+
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f32, f32); 3]>,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vec3; 3], material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    pub fn arc(vertices: [Vec3; 3], material: Arc<dyn Material>) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(vertices, material))
+    }
+
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [(f32, f32); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    fn area(&self) -> f32 {
+        let [p0, p1, p2] = self.vertices;
+        0.5 * (p1 - p0).cross(&(p2 - p0)).length()
+    }
+}
+
+impl Hitable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        let [p0, p1, p2] = self.vertices;
+        let (distance, b1, b2) = intersect_triangle(ray, p0, p1, p2, t_min, t_max)?;
+        let normal = match self.normals {
+            Some([n0, n1, n2]) => interpolate_normal(n0, n1, n2, b1, b2),
+            None => (p1 - p0).cross(&(p2 - p0)).unit(),
+        };
+        let (u, v) = match self.uvs {
+            Some([uv0, uv1, uv2]) => interpolate_uv(uv0, uv1, uv2, b1, b2),
+            None => (b1, b2),
+        };
+        Some(RayHit {
+            distance,
+            point: ray.point_at(distance),
+            normal,
+            u,
+            v,
+            material: self.material.clone(),
+        })
+    }
+
+    fn sample_direction(&self, origin: &Vec3) -> Option<Vec3> {
+        self.sample_surface()
+            .map(|sample| sample.hit.point - origin)
+    }
+
+    fn direction_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |hit| solid_angle_pdf(ray, &hit, self.area()))
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let [p0, p1, p2] = self.vertices;
+        let mut rng = random::rng();
+        let (mut b1, mut b2) = (rng.gen::<f32>(), rng.gen::<f32>());
+        // Reflecting points beyond the far edge keeps the distribution uniform
+        if b1 + b2 > 1.0 {
+            b1 = 1.0 - b1;
+            b2 = 1.0 - b2;
+        }
+        let normal = match self.normals {
+            Some([n0, n1, n2]) => interpolate_normal(n0, n1, n2, b1, b2),
+            None => (p1 - p0).cross(&(p2 - p0)).unit(),
+        };
+        let (u, v) = match self.uvs {
+            Some([uv0, uv1, uv2]) => interpolate_uv(uv0, uv1, uv2, b1, b2),
+            None => (b1, b2),
+        };
+        Some(SurfaceSample {
+            hit: RayHit {
+                distance: 0.0,
+                point: p0 + b1 * (p1 - p0) + b2 * (p2 - p0),
+                normal,
+                u,
+                v,
+                material: self.material.clone(),
+            },
+            pdf: 1.0 / self.area(),
+        })
+    }
+
+    fn surface_pdf(&self, ray: &Ray) -> f32 {
+        self.hit(ray, 0.0001, f32::MAX)
+            .map_or(0.0, |_| 1.0 / self.area())
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        let [p0, p1, p2] = self.vertices;
+        Some(triangle_bounds(p0, p1, p2))
+    }
+}
+
+/// Möller-Trumbore intersection, returning the distance and the barycentric
+/// weights of the second and third vertices.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    p0: Vec3,
+    p1: Vec3,
+    p2: Vec3,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32, f32)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let pvec = ray.direction.cross(&edge2);
+    let det = edge1.dot(&pvec);
+    if det.abs() < TRIANGLE_EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let tvec = ray.origin - p0;
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+    let qvec = tvec.cross(&edge1);
+    let b2 = ray.direction.dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(&qvec) * inv_det;
+    if distance < t_max && distance > t_min {
+        Some((distance, b1, b2))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn interpolate_normal(n0: Vec3, n1: Vec3, n2: Vec3, b1: f32, b2: f32) -> Vec3 {
+    ((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2).unit()
+}
+
+pub(crate) fn interpolate_uv(
+    uv0: (f32, f32),
+    uv1: (f32, f32),
+    uv2: (f32, f32),
+    b1: f32,
+    b2: f32,
+) -> (f32, f32) {
+    let b0 = 1.0 - b1 - b2;
+    (
+        b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+        b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+    )
+}
+
+// Axis aligned triangles have a flat box, which the slab test never reports as hit,
+// so every box is padded slightly.
+pub(crate) fn triangle_bounds(p0: Vec3, p1: Vec3, p2: Vec3) -> AABB {
+    let padding = Vec3::new(BOX_PADDING, BOX_PADDING, BOX_PADDING);
+    let min = Vec3::new(
+        p0.x().min(p1.x()).min(p2.x()),
+        p0.y().min(p1.y()).min(p2.y()),
+        p0.z().min(p1.z()).min(p2.z()),
+    );
+    let max = Vec3::new(
+        p0.x().max(p1.x()).max(p2.x()),
+        p0.y().max(p1.y()).max(p2.y()),
+        p0.z().max(p1.z()).max(p2.z()),
+    );
+    AABB::new(min - padding, max + padding)
+}
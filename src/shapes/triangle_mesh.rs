@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use crate::{
+    hitable::{Hitable, RayHit},
+    material::Material,
+    structures::{Ray, Vec3, AABB},
+};
+
+use super::triangle::{interpolate_normal, interpolate_uv, intersect_triangle, triangle_bounds};
+
+const MAX_TRIANGLES_PER_LEAF: usize = 4;
+const MAX_TRAVERSAL_DEPTH: usize = 64;
+
+#[derive(Copy, Clone)]
+struct MeshNode {
+    bounding_box: AABB,
+    // First triangle of a leaf, or the index of the right child of an interior node.
+    // The left child of an interior node always directly follows it.
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    indices: Vec<[u32; 3]>,
+    nodes: Vec<MeshNode>,
+    material: Arc<dyn Material>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        if let Some(normals) = &normals {
+            assert_eq!(
+                normals.len(),
+                positions.len(),
+                "A mesh must have one normal per vertex"
+            );
+        }
+        if let Some(uvs) = &uvs {
+            assert_eq!(
+                uvs.len(),
+                positions.len(),
+                "A mesh must have one UV per vertex"
+            );
+        }
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&index| (index as usize) < positions.len()),
+            "All mesh indices must reference a vertex"
+        );
+
+        let mut mesh = Self {
+            positions,
+            normals,
+            uvs,
+            indices,
+            nodes: Vec::new(),
+            material,
+        };
+        mesh.build_nodes();
+        mesh
+    }
+
+    pub fn arc(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(positions, normals, uvs, indices, material))
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn vertices(&self, triangle: usize) -> (Vec3, Vec3, Vec3) {
+        let [i0, i1, i2] = self.indices[triangle];
+        (
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        )
+    }
+
+    fn build_nodes(&mut self) {
+        if self.indices.is_empty() {
+            return;
+        }
+        let bounds: Vec<AABB> = (0..self.indices.len())
+            .map(|triangle| {
+                let (p0, p1, p2) = self.vertices(triangle);
+                triangle_bounds(p0, p1, p2)
+            })
+            .collect();
+        let centroids: Vec<Vec3> = bounds.iter().map(|b| 0.5 * (b.min + b.max)).collect();
+        let mut order: Vec<usize> = (0..self.indices.len()).collect();
+        let mut nodes = Vec::with_capacity(2 * self.indices.len() / MAX_TRIANGLES_PER_LEAF + 1);
+        build_recursive(&bounds, &centroids, &mut order, 0, &mut nodes);
+        self.indices = order
+            .iter()
+            .map(|&triangle| self.indices[triangle])
+            .collect();
+        self.nodes = nodes;
+    }
+
+    fn hit_triangle(&self, triangle: usize, ray: &Ray, distance: f32, b1: f32, b2: f32) -> RayHit {
+        let [i0, i1, i2] = self.indices[triangle];
+        let (i0, i1, i2) = (i0 as usize, i1 as usize, i2 as usize);
+        let normal = match &self.normals {
+            Some(normals) => interpolate_normal(normals[i0], normals[i1], normals[i2], b1, b2),
+            None => {
+                let (p0, p1, p2) = self.vertices(triangle);
+                (p1 - p0).cross(&(p2 - p0)).unit()
+            }
+        };
+        let (u, v) = match &self.uvs {
+            Some(uvs) => interpolate_uv(uvs[i0], uvs[i1], uvs[i2], b1, b2),
+            None => (b1, b2),
+        };
+        RayHit {
+            distance,
+            point: ray.point_at(distance),
+            normal,
+            u,
+            v,
+            material: self.material.clone(),
+        }
+    }
+}
+
+fn build_recursive(
+    bounds: &[AABB],
+    centroids: &[Vec3],
+    order: &mut [usize],
+    offset: usize,
+    nodes: &mut Vec<MeshNode>,
+) -> usize {
+    let bounding_box = order[1..].iter().fold(bounds[order[0]], |acc, &triangle| {
+        AABB::surrounding_box(&acc, &bounds[triangle])
+    });
+    let node_index = nodes.len();
+    if order.len() <= MAX_TRIANGLES_PER_LEAF {
+        nodes.push(MeshNode {
+            bounding_box,
+            offset,
+            count: order.len(),
+            axis: 0,
+        });
+        return node_index;
+    }
+
+    let centroid_box = order.iter().fold(
+        AABB::new(centroids[order[0]], centroids[order[0]]),
+        |acc, &triangle| {
+            AABB::surrounding_box(&acc, &AABB::new(centroids[triangle], centroids[triangle]))
+        },
+    );
+    let extent = centroid_box.max - centroid_box.min;
+    let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+        0
+    } else if extent.y() > extent.z() {
+        1
+    } else {
+        2
+    };
+
+    let mid = order.len() / 2;
+    order.select_nth_unstable_by(mid, |&a, &b| {
+        centroids[a][axis].total_cmp(&centroids[b][axis])
+    });
+
+    nodes.push(MeshNode {
+        bounding_box,
+        offset: 0,
+        count: 0,
+        axis,
+    });
+    let (left, right) = order.split_at_mut(mid);
+    build_recursive(bounds, centroids, left, offset, nodes);
+    let right_index = build_recursive(bounds, centroids, right, offset + mid, nodes);
+    nodes[node_index].offset = right_index;
+    node_index
+}
+
+impl Hitable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        self.closest_hit(ray, t_min, t_max, None)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        let mut cost = 0;
+        self.closest_hit(ray, t_min, t_max, Some(&mut cost));
+        cost
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        self.nodes.first().map(|node| node.bounding_box)
+    }
+}
+
+impl TriangleMesh {
+    // Counts bounding box and triangle tests into `cost` when it is given
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut cost: Option<&mut usize>,
+    ) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut closest_so_far = t_max;
+        let mut best_hit: Option<(usize, f32, f32, f32)> = None;
+        let mut stack = [0usize; MAX_TRAVERSAL_DEPTH];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node_index = stack[stack_len];
+            let node = &self.nodes[node_index];
+            if let Some(cost) = cost.as_deref_mut() {
+                *cost += 1;
+            }
+            if !node.bounding_box.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count > 0 {
+                if let Some(cost) = cost.as_deref_mut() {
+                    *cost += node.count;
+                }
+                for triangle in node.offset..node.offset + node.count {
+                    let (p0, p1, p2) = self.vertices(triangle);
+                    if let Some((distance, b1, b2)) =
+                        intersect_triangle(ray, p0, p1, p2, t_min, closest_so_far)
+                    {
+                        closest_so_far = distance;
+                        best_hit = Some((triangle, distance, b1, b2));
+                    }
+                }
+            } else {
+                // Push the far child first so the near child is visited first and
+                // shrinks closest_so_far before the far child is tested.
+                let left = node_index + 1;
+                let right = node.offset;
+                let (near, far) = if ray.direction[node.axis] < 0.0 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                stack[stack_len] = far;
+                stack[stack_len + 1] = near;
+                stack_len += 2;
+            }
+        }
+        best_hit
+            .map(|(triangle, distance, b1, b2)| self.hit_triangle(triangle, ray, distance, b1, b2))
+    }
+}
@@ -0,0 +1,248 @@
+use std::{f32::consts, sync::Arc};
+
+use rand::Rng;
+
+use crate::{
+    colour::blackbody,
+    hitable::{Hitable, RayHit},
+    material::{Material, MaterialSample},
+    random,
+    structures::{Ray, Vec3, VoxelGrid, AABB},
+};
+
+// Temperatures between zero and the hottest voxel with a precomputed collision material
+const EMISSION_LEVELS: usize = 256;
+// Below this transmittance, shadow rays are randomly ended rather than tracked further
+const ROULETTE_TRANSMITTANCE: f32 = 0.1;
+
+/// A heterogeneous participating medium, such as a cloud or an explosion, with its
+/// density taken from a voxel grid stretched over an axis aligned box.
+///
+/// Rays are delta tracked through the box against a majorant, the grid's highest density,
+/// and shadow rays use ratio tracking to estimate how much light passes through.
+pub struct VoxelVolume {
+    grid: Arc<VoxelGrid>,
+    bounds: AABB,
+    density: f32,
+    majorant: f32,
+    albedo: Vec3,
+    temperature_scale: f32,
+    max_temperature: f32,
+    // Collision materials at evenly spaced temperatures up to the hottest voxel's, or a
+    // single one if the volume doesn't glow
+    phases: Vec<Arc<dyn Material>>,
+}
+
+impl VoxelVolume {
+    /// The grid's densities are multiplied by `density`, giving the chance of a collision
+    /// per unit distance. At each collision light is scattered with probability `albedo`
+    /// and otherwise absorbed.
+    pub fn new(grid: Arc<VoxelGrid>, min: Vec3, max: Vec3, density: f32, albedo: Vec3) -> Self {
+        assert!(
+            (0..3).all(|axis| min[axis] < max[axis]),
+            "A voxel volume's box must have a positive size along each axis"
+        );
+        assert!(
+            density > 0.0,
+            "A voxel volume's density must be greater than zero"
+        );
+        Self {
+            majorant: grid.max_density() * density,
+            grid,
+            bounds: AABB::new(min, max),
+            density,
+            albedo,
+            temperature_scale: 1.0,
+            max_temperature: 0.0,
+            phases: vec![Arc::new(Collision {
+                albedo,
+                emission: Vec3::new(0.0, 0.0, 0.0),
+            })],
+        }
+    }
+
+    pub fn arc(
+        grid: Arc<VoxelGrid>,
+        min: Vec3,
+        max: Vec3,
+        density: f32,
+        albedo: Vec3,
+    ) -> Arc<dyn Hitable> {
+        Arc::new(Self::new(grid, min, max, density, albedo))
+    }
+
+    /// Makes absorbing collisions glow like a black body, with the grid's temperatures
+    /// multiplied by `temperature_scale` to give kelvin. The hottest voxel emits with a
+    /// luminance of `intensity`, and cooler ones fall off with the fourth power of their
+    /// temperature, as a black body's total emission does.
+    ///
+    /// Panics if the grid has no temperatures.
+    pub fn with_emission(mut self, intensity: f32, temperature_scale: f32) -> Self {
+        assert!(
+            self.grid.has_temperature(),
+            "Emission requires a grid with temperatures"
+        );
+        self.temperature_scale = temperature_scale;
+        self.max_temperature = self.grid.max_temperature() * temperature_scale;
+        let last = (EMISSION_LEVELS - 1) as f32;
+        let absorbed = Vec3::new(1.0, 1.0, 1.0) - self.albedo;
+        self.phases = (0..EMISSION_LEVELS)
+            .map(|index| {
+                let fraction = index as f32 / last;
+                let emission =
+                    intensity * fraction.powi(4) * blackbody(fraction * self.max_temperature);
+                Arc::new(Collision {
+                    albedo: self.albedo,
+                    emission: absorbed * emission,
+                }) as Arc<dyn Material>
+            })
+            .collect();
+        self
+    }
+
+    // The part of the ray between t_min and t_max which lies inside the box
+    fn span(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        let (mut start, mut end) = (t_min.max(0.0), t_max);
+        for axis in 0..3 {
+            let inverse_direction = 1.0 / ray.direction[axis];
+            let mut t0 = (self.bounds.min[axis] - ray.origin[axis]) * inverse_direction;
+            let mut t1 = (self.bounds.max[axis] - ray.origin[axis]) * inverse_direction;
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaNs, from rays lying in the plane of a face, leave the span unchanged
+            start = if t0 > start { t0 } else { start };
+            end = if t1 < end { t1 } else { end };
+        }
+        (start < end).then_some((start, end))
+    }
+
+    fn local_point(&self, point: &Vec3) -> Vec3 {
+        (point - self.bounds.min) / (self.bounds.max - self.bounds.min)
+    }
+
+    // The collision material for a point's temperature. Between two levels one is picked
+    // at random in proportion to how close it is, so the emission is interpolated on
+    // average.
+    fn phase(&self, local_point: &Vec3) -> Arc<dyn Material> {
+        if self.phases.len() == 1 || self.max_temperature <= 0.0 {
+            return self.phases[0].clone();
+        }
+        let temperature = self.grid.temperature(local_point) * self.temperature_scale;
+        let position =
+            (temperature / self.max_temperature).clamp(0.0, 1.0) * (EMISSION_LEVELS - 1) as f32;
+        let mut index = (position as usize).min(EMISSION_LEVELS - 2);
+        if random::rng().gen::<f32>() < position - index as f32 {
+            index += 1;
+        }
+        self.phases[index].clone()
+    }
+
+    // Distance along the ray, in units of its direction, to the next tentative collision
+    fn step(&self, ray_length: f32) -> f32 {
+        -(1.0 - random::rng().gen::<f32>()).ln() / (self.majorant * ray_length)
+    }
+}
+
+impl Hitable for VoxelVolume {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let (start, end) = self.span(ray, t_min, t_max)?;
+        let ray_length = ray.direction.length();
+        let mut rng = random::rng();
+        let mut distance = start;
+        loop {
+            distance += self.step(ray_length);
+            if distance >= end {
+                return None;
+            }
+            let point = ray.point_at(distance);
+            let local_point = self.local_point(&point);
+            let density = self.grid.density(&local_point) * self.density;
+            // Collisions with the difference between the density and the majorant are
+            // null collisions, which leave the ray unchanged
+            if rng.gen::<f32>() * self.majorant < density {
+                return Some(RayHit {
+                    distance,
+                    point,
+                    // Scattering in a medium does not depend on the normal, so any will do
+                    normal: Vec3::new(1.0, 0.0, 0.0),
+                    u: 0.0,
+                    v: 0.0,
+                    material: self.phase(&local_point),
+                });
+            }
+        }
+    }
+
+    // Ratio tracking, weighting by the chance of each tentative collision being null
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        if self.majorant <= 0.0 {
+            return 1.0;
+        }
+        let (start, end) = match self.span(ray, t_min, t_max) {
+            Some(span) => span,
+            None => return 1.0,
+        };
+        let ray_length = ray.direction.length();
+        let mut rng = random::rng();
+        let mut transmittance = 1.0;
+        let mut distance = start;
+        loop {
+            distance += self.step(ray_length);
+            if distance >= end {
+                return transmittance;
+            }
+            let density = self
+                .grid
+                .density(&self.local_point(&ray.point_at(distance)));
+            transmittance *= 1.0 - density * self.density / self.majorant;
+            if transmittance < ROULETTE_TRANSMITTANCE {
+                if rng.gen::<f32>() >= transmittance {
+                    return 0.0;
+                }
+                transmittance = 1.0;
+            }
+        }
+    }
+
+    fn bounding_box(&self, _t0: f32, _t1: f32) -> Option<AABB> {
+        Some(self.bounds)
+    }
+}
+
+// A real collision inside the volume, which scatters isotropically and emits the light
+// absorbed there
+struct Collision {
+    albedo: Vec3,
+    emission: Vec3,
+}
+
+impl Material for Collision {
+    fn sample(&self, _in_ray: &Ray, _hit: &RayHit) -> Option<MaterialSample> {
+        Some(MaterialSample {
+            direction: Vec3::get_point_on_unit_sphere(),
+            weight: self.albedo,
+            pdf: 1.0 / (4.0 * consts::PI),
+            specular: false,
+        })
+    }
+
+    fn emitted(&self, _hit: &RayHit) -> Vec3 {
+        self.emission
+    }
+
+    fn eval(&self, in_ray: &Ray, hit: &RayHit, direction: &Vec3) -> Vec3 {
+        self.albedo * self.pdf(in_ray, hit, direction)
+    }
+
+    fn pdf(&self, _in_ray: &Ray, _hit: &RayHit, _direction: &Vec3) -> f32 {
+        1.0 / (4.0 * consts::PI)
+    }
+
+    fn is_volumetric(&self) -> bool {
+        true
+    }
+}
@@ -2,6 +2,7 @@ use std::mem::swap;
 
 use super::{Ray, Vec3};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone)]
 pub struct AABB {
     pub min: Vec3,
@@ -13,7 +14,7 @@ impl AABB {
         Self { min, max }
     }
 
-    pub fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> bool {
+    pub fn hit(&self, ray: &Ray, mut t_min: f32, mut t_max: f32) -> bool {
         for i in 0..3 {
             let inv_dir = 1.0 / ray.direction[i];
             let mut t0 = (self.min[i] - ray.origin[i]) * inv_dir;
@@ -21,8 +22,9 @@ impl AABB {
             if inv_dir < 0.0 {
                 swap(&mut t0, &mut t1);
             }
-            let t_min = f32::max(t0, t_min);
-            let t_max = f32::min(t1, t_max);
+            t_min = f32::max(t0, t_min);
+            t_max = f32::min(t1, t_max);
+
             if t_max <= t_min {
                 return false;
             }
@@ -30,6 +32,15 @@ impl AABB {
         true
     }
 
+    pub fn centre(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.max - self.min;
+        2.0 * (extent.x() * extent.y() + extent.y() * extent.z() + extent.z() * extent.x())
+    }
+
     pub fn surrounding_box(box0: &AABB, box1: &AABB) -> AABB {
         let min = Vec3::new(
             f32::min(box0.min.x(), box1.min.x()),
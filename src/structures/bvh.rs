@@ -1,119 +1,642 @@
-use std::{cmp::Ordering, sync::Arc};
+use std::{fmt::Display, mem, sync::Arc};
 
-use rand::{thread_rng, Rng};
+use rayon::{
+    iter::{IntoParallelIterator, ParallelIterator},
+    slice::ParallelSlice,
+};
 
-use crate::hitable::Hitable;
+use crate::hitable::{Hitable, RayHit};
 
-use super::AABB;
+use super::{Ray, Vec3, AABB};
 
+const BIN_COUNT: usize = 16;
+const MAX_LEAF_SIZE: usize = 4;
+// Relative to the cost of intersecting one primitive
+const TRAVERSAL_COST: f32 = 0.125;
+const MAX_TRAVERSAL_DEPTH: usize = 64;
+// Deepest a leaf may be, counting the root as depth zero, so that traversal never holds
+// more nodes than fit on its stack: one sibling for each level above, and two children
+const MAX_DEPTH: usize = MAX_TRAVERSAL_DEPTH - 1;
+// Nodes with more items than this are binned in parallel chunks of this size
+const BINNING_CHUNK_SIZE: usize = 16 * 1024;
+// Nodes with fewer items than this have their whole subtree built by one thread, depth
+// first, so the items stay in cache. Larger nodes are split a level at a time.
+const SUBTREE_THRESHOLD: usize = 4 * 1024;
+
+/// Bounding volume hierarchy built with the binned surface area heuristic, stored as a
+/// flat array of nodes. The same items always produce the same tree.
 pub struct BvhNode {
-    left: Arc<dyn Hitable>,
-    right: Arc<dyn Hitable>,
+    nodes: Vec<Node>,
+    primitives: Vec<Arc<dyn Hitable>>,
+}
+
+#[derive(Copy, Clone)]
+struct Node {
+    bounding_box: AABB,
+    // First primitive of a leaf, or the index of the left child of an interior node.
+    // The right child of an interior node always directly follows the left.
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+/// Reported by [`BvhNode::with_progress`] as each level of the tree is completed.
+pub struct BvhBuildProgress {
+    pub level: usize,
+    pub level_node_count: usize,
+    pub primitives_in_leaves: usize,
+    pub primitive_count: usize,
+}
+
+struct BuildItem {
+    hitable: Arc<dyn Hitable>,
     bounding_box: AABB,
+    centroid: Vec3,
+}
+
+// A node whose items have not yet been split, covering `start..end` of the build items
+struct BuildTask {
+    node_index: usize,
+    start: usize,
+    end: usize,
+}
+
+enum TaskResult {
+    // The bounds of the node and, unless it is a leaf, its split axis and the number of
+    // items partitioned to the left
+    Split(AABB, Option<(usize, usize)>),
+    // Nodes with indices relative to the subtree root and primitive offsets relative to
+    // the task's first item
+    Subtree(Vec<Node>),
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    count: usize,
+    bounding_box: Option<AABB>,
 }
 
 impl BvhNode {
     pub fn new(items: &[Arc<dyn Hitable>], time0: f32, time1: f32) -> Self {
-        let mut rng = thread_rng();
-        let axis: i32 = (3.0 * rng.gen::<f32>()) as i32;
-        let mut items = Vec::from(items);
-        if axis == 0 {
-            items.sort_by(|a, b| BvhNode::box_x_compare(a, b))
-        } else if axis == 1 {
-            items.sort_by(|a, b| BvhNode::box_y_compare(a, b))
-        } else {
-            items.sort_by(|a, b| BvhNode::box_z_compare(a, b))
-        };
-        let (left, right) = if items.len() == 1 {
-            (items[0].clone(), items[0].clone())
-        } else if items.len() == 2 {
-            (items[0].clone(), items[1].clone())
-        } else {
-            (
-                Arc::new(BvhNode::new(&items[0..items.len() / 2], time0, time1))
-                    as Arc<dyn Hitable>,
-                Arc::new(BvhNode::new(
-                    &items[(items.len() / 2)..items.len()],
-                    time0,
-                    time1,
-                )) as Arc<dyn Hitable>,
-            )
-        };
-        let bounding_box = if let (Some(left_box), Some(right_box)) = (
-            left.bounding_box(time0, time1),
-            right.bounding_box(time0, time1),
-        ) {
-            AABB::surrounding_box(&left_box, &right_box)
-        } else {
-            panic!("All Hitables in a BVH must be able to produce a bounding box")
+        Self::with_progress(items, time0, time1, |_| {})
+    }
+
+    /// Builds the tree a level at a time on the current rayon pool, calling `progress`
+    /// after each level.
+    pub fn with_progress(
+        items: &[Arc<dyn Hitable>],
+        time0: f32,
+        time1: f32,
+        mut progress: impl FnMut(&BvhBuildProgress),
+    ) -> Self {
+        assert!(!items.is_empty(), "A BVH must contain at least one Hitable");
+        let mut items: Vec<BuildItem> = items
+            .into_par_iter()
+            .map(|hitable| {
+                let bounding_box = hitable
+                    .bounding_box(time0, time1)
+                    .expect("All Hitables in a BVH must be able to produce a bounding box");
+                BuildItem {
+                    hitable: hitable.clone(),
+                    bounding_box,
+                    centroid: bounding_box.centre(),
+                }
+            })
+            .collect();
+
+        let placeholder = Node {
+            bounding_box: items[0].bounding_box,
+            offset: 0,
+            count: 0,
+            axis: 0,
         };
+        let mut nodes = Vec::with_capacity(2 * items.len() - 1);
+        nodes.push(placeholder);
+        let mut tasks = vec![BuildTask {
+            node_index: 0,
+            start: 0,
+            end: items.len(),
+        }];
+        let mut level = 0;
+        let mut primitives_in_leaves = 0;
+        while !tasks.is_empty() {
+            let results: Vec<TaskResult> = task_slices(&mut items, &tasks)
+                .into_par_iter()
+                .map(|items| {
+                    if items.len() <= SUBTREE_THRESHOLD {
+                        let mut subtree = Vec::with_capacity(2 * items.len() - 1);
+                        subtree.push(placeholder);
+                        build_subtree(items, 0, 0, level, &mut subtree);
+                        TaskResult::Subtree(subtree)
+                    } else {
+                        let (bounding_box, split) = split_node(items, level);
+                        TaskResult::Split(bounding_box, split)
+                    }
+                })
+                .collect();
+
+            let mut next_tasks = Vec::with_capacity(2 * tasks.len());
+            for (task, result) in tasks.iter().zip(results) {
+                match result {
+                    TaskResult::Split(bounding_box, Some((axis, mid))) => {
+                        let left = nodes.len();
+                        nodes.push(placeholder);
+                        nodes.push(placeholder);
+                        next_tasks.push(BuildTask {
+                            node_index: left,
+                            start: task.start,
+                            end: task.start + mid,
+                        });
+                        next_tasks.push(BuildTask {
+                            node_index: left + 1,
+                            start: task.start + mid,
+                            end: task.end,
+                        });
+                        nodes[task.node_index] = Node {
+                            bounding_box,
+                            offset: left,
+                            count: 0,
+                            axis,
+                        };
+                    }
+                    TaskResult::Split(bounding_box, None) => {
+                        primitives_in_leaves += task.end - task.start;
+                        nodes[task.node_index] = Node {
+                            bounding_box,
+                            offset: task.start,
+                            count: task.end - task.start,
+                            axis: 0,
+                        };
+                    }
+                    TaskResult::Subtree(subtree) => {
+                        // The subtree root takes the task's node, and the rest follow on
+                        // from the end of the array in the same order
+                        let base = nodes.len() - 1;
+                        for (index, node) in subtree.iter().enumerate() {
+                            let node = Node {
+                                offset: if node.count > 0 {
+                                    task.start + node.offset
+                                } else {
+                                    base + node.offset
+                                },
+                                ..*node
+                            };
+                            if index == 0 {
+                                nodes[task.node_index] = node;
+                            } else {
+                                nodes.push(node);
+                            }
+                        }
+                        primitives_in_leaves += task.end - task.start;
+                    }
+                }
+            }
+            progress(&BvhBuildProgress {
+                level,
+                level_node_count: tasks.len(),
+                primitives_in_leaves,
+                primitive_count: items.len(),
+            });
+            tasks = next_tasks;
+            level += 1;
+        }
+
         Self {
-            left,
-            right,
-            bounding_box,
+            nodes,
+            primitives: items.into_iter().map(|item| item.hitable).collect(),
         }
     }
 
-    fn box_x_compare(a: &Arc<dyn Hitable>, b: &Arc<dyn Hitable>) -> Ordering {
-        if let (Some(a_box), Some(b_box)) = (a.bounding_box(0.0, 0.0), b.bounding_box(0.0, 0.0)) {
-            if a_box.min.x() - b_box.min.x() < 0.0 {
-                Ordering::Less
-            } else {
-                Ordering::Greater
-            }
+    pub fn stats(&self) -> BvhStats {
+        let mut stats = BvhStats {
+            node_count: 0,
+            leaf_count: 0,
+            depth: 0,
+            min_leaf_size: usize::MAX,
+            max_leaf_size: 0,
+            primitive_count: 0,
+            sah_cost: 0.0,
+        };
+        self.accumulate_stats(0, &mut stats, 1, self.nodes[0].bounding_box.surface_area());
+        stats
+    }
+
+    fn accumulate_stats(
+        &self,
+        node_index: usize,
+        stats: &mut BvhStats,
+        depth: usize,
+        root_area: f32,
+    ) {
+        let node = &self.nodes[node_index];
+        stats.node_count += 1;
+        stats.depth = stats.depth.max(depth);
+        let relative_area = node.bounding_box.surface_area() / root_area;
+        if node.count > 0 {
+            stats.leaf_count += 1;
+            stats.primitive_count += node.count;
+            stats.min_leaf_size = stats.min_leaf_size.min(node.count);
+            stats.max_leaf_size = stats.max_leaf_size.max(node.count);
+            stats.sah_cost += relative_area * node.count as f32;
         } else {
-            panic!("");
+            stats.sah_cost += relative_area * TRAVERSAL_COST;
+            self.accumulate_stats(node.offset, stats, depth + 1, root_area);
+            self.accumulate_stats(node.offset + 1, stats, depth + 1, root_area);
         }
     }
+}
 
-    fn box_y_compare(a: &Arc<dyn Hitable>, b: &Arc<dyn Hitable>) -> Ordering {
-        if let (Some(a_box), Some(b_box)) = (a.bounding_box(0.0, 0.0), b.bounding_box(0.0, 0.0)) {
-            if a_box.min.y() - b_box.min.y() < 0.0 {
-                Ordering::Less
-            } else {
-                Ordering::Greater
+// Tasks are always in order of their items and never overlap, so the items can be
+// handed out as disjoint slices to be split in parallel
+fn task_slices<'a>(items: &'a mut [BuildItem], tasks: &[BuildTask]) -> Vec<&'a mut [BuildItem]> {
+    let mut rest = items;
+    let mut consumed = 0;
+    let mut slices = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let (_, tail) = mem::take(&mut rest).split_at_mut(task.start - consumed);
+        let (slice, tail) = tail.split_at_mut(task.end - task.start);
+        slices.push(slice);
+        rest = tail;
+        consumed = task.end;
+    }
+    slices
+}
+
+fn build_subtree(
+    items: &mut [BuildItem],
+    offset: usize,
+    node_index: usize,
+    depth: usize,
+    nodes: &mut Vec<Node>,
+) {
+    let (bounding_box, split) = split_node(items, depth);
+    nodes[node_index] = match split {
+        Some((axis, mid)) => {
+            let left = nodes.len();
+            nodes.push(nodes[node_index]);
+            nodes.push(nodes[node_index]);
+            let (left_items, right_items) = items.split_at_mut(mid);
+            build_subtree(left_items, offset, left, depth + 1, nodes);
+            build_subtree(right_items, offset + mid, left + 1, depth + 1, nodes);
+            Node {
+                bounding_box,
+                offset: left,
+                count: 0,
+                axis,
             }
-        } else {
-            panic!("");
         }
+        None => Node {
+            bounding_box,
+            offset,
+            count: items.len(),
+            axis: 0,
+        },
+    };
+}
+
+// Returns the bounds of the items and, unless they should form a leaf, the axis they
+// were split on and the number that were partitioned to the left
+fn split_node(items: &mut [BuildItem], depth: usize) -> (AABB, Option<(usize, usize)>) {
+    let (bounding_box, centroid_box) = item_bounds(items);
+    if items.len() == 1 {
+        return (bounding_box, None);
+    }
+
+    // Items spread very unevenly, such as at exponentially growing distances, can have
+    // the heuristic peel a few off at each level. Near the depth limit the items are
+    // halved instead, which leaves just enough levels to separate them all
+    let halvings = items.len().next_power_of_two().trailing_zeros() as usize;
+    if depth + halvings >= MAX_DEPTH {
+        let extent = centroid_box.max - centroid_box.min;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap();
+        let mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |a, b| a.centroid[axis].total_cmp(&b.centroid[axis]));
+        return (bounding_box, Some((axis, mid)));
     }
 
-    fn box_z_compare(a: &Arc<dyn Hitable>, b: &Arc<dyn Hitable>) -> Ordering {
-        if let (Some(a_box), Some(b_box)) = (a.bounding_box(0.0, 0.0), b.bounding_box(0.0, 0.0)) {
-            if a_box.min.z() - b_box.min.z() < 0.0 {
-                Ordering::Less
+    let node_area = bounding_box.surface_area();
+    let split = (0..3)
+        .filter(|&axis| centroid_box.max[axis] > centroid_box.min[axis])
+        .filter_map(|axis| best_split(items, node_area, &centroid_box, axis))
+        .min_by(|a, b| a.2.total_cmp(&b.2));
+
+    let leaf_cost = items.len() as f32;
+    let split = match split {
+        Some((axis, bin, cost)) if cost < leaf_cost || items.len() > MAX_LEAF_SIZE => Some((
+            axis,
+            partition(items, |item| bin_index(item, &centroid_box, axis) <= bin),
+        )),
+        // Every centroid is in the same place, so there is nothing for the bins to separate
+        None if items.len() > MAX_LEAF_SIZE => Some((0, items.len() / 2)),
+        _ => None,
+    };
+    (bounding_box, split)
+}
+
+// The bounds of the items' boxes and of their centroids
+fn item_bounds(items: &[BuildItem]) -> (AABB, AABB) {
+    let chunk_bounds = |items: &[BuildItem]| {
+        items.iter().fold(
+            (
+                items[0].bounding_box,
+                AABB::new(items[0].centroid, items[0].centroid),
+            ),
+            |(bounds, centroids), item| {
+                (
+                    AABB::surrounding_box(&bounds, &item.bounding_box),
+                    AABB::surrounding_box(&centroids, &AABB::new(item.centroid, item.centroid)),
+                )
+            },
+        )
+    };
+    if items.len() <= BINNING_CHUNK_SIZE {
+        chunk_bounds(items)
+    } else {
+        items
+            .par_chunks(BINNING_CHUNK_SIZE)
+            .map(chunk_bounds)
+            .reduce_with(|a, b| {
+                (
+                    AABB::surrounding_box(&a.0, &b.0),
+                    AABB::surrounding_box(&a.1, &b.1),
+                )
+            })
+            .unwrap()
+    }
+}
+
+// Returns the axis, the last bin on the left of the split and the cost of splitting there,
+// relative to the surface area of the node
+fn best_split(
+    items: &[BuildItem],
+    node_area: f32,
+    centroid_box: &AABB,
+    axis: usize,
+) -> Option<(usize, usize, f32)> {
+    let bins = fill_bins(items, centroid_box, axis);
+
+    // Sweep from the right first so each split can be costed in a single pass from the left
+    let mut right_costs = [0.0; BIN_COUNT];
+    let mut right_box: Option<AABB> = None;
+    let mut right_count = 0;
+    for bin in (1..BIN_COUNT).rev() {
+        right_box = merge(right_box, bins[bin].bounding_box);
+        right_count += bins[bin].count;
+        right_costs[bin - 1] = area(right_box) * right_count as f32;
+    }
+
+    let mut left_box: Option<AABB> = None;
+    let mut left_count = 0;
+    let mut best: Option<(usize, usize, f32)> = None;
+    for bin in 0..BIN_COUNT - 1 {
+        left_box = merge(left_box, bins[bin].bounding_box);
+        left_count += bins[bin].count;
+        if left_count == 0 || left_count == items.len() {
+            continue;
+        }
+        let cost =
+            TRAVERSAL_COST + (area(left_box) * left_count as f32 + right_costs[bin]) / node_area;
+        if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+            best = Some((axis, bin, cost));
+        }
+    }
+    best
+}
+
+fn fill_bins(items: &[BuildItem], centroid_box: &AABB, axis: usize) -> [Bin; BIN_COUNT] {
+    let chunk_bins = |items: &[BuildItem]| {
+        let mut bins = [Bin {
+            count: 0,
+            bounding_box: None,
+        }; BIN_COUNT];
+        for item in items {
+            let bin = &mut bins[bin_index(item, centroid_box, axis)];
+            bin.count += 1;
+            bin.bounding_box = merge(bin.bounding_box, Some(item.bounding_box));
+        }
+        bins
+    };
+    if items.len() <= BINNING_CHUNK_SIZE {
+        chunk_bins(items)
+    } else {
+        // Counts and box unions don't depend on the order they are combined in, so the
+        // bins, and the tree, are the same however the work is divided
+        items
+            .par_chunks(BINNING_CHUNK_SIZE)
+            .map(chunk_bins)
+            .reduce_with(|mut a, b| {
+                for (a, b) in a.iter_mut().zip(b) {
+                    a.count += b.count;
+                    a.bounding_box = merge(a.bounding_box, b.bounding_box);
+                }
+                a
+            })
+            .unwrap()
+    }
+}
+
+impl BvhNode {
+    // Counts intersection tests into `cost` when it is given, including those made by
+    // the primitives themselves
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        mut cost: Option<&mut usize>,
+    ) -> Option<RayHit> {
+        let mut closest_so_far = t_max;
+        let mut best_hit = None;
+        let mut stack = [0usize; MAX_TRAVERSAL_DEPTH];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node_index = stack[stack_len];
+            let node = &self.nodes[node_index];
+            if let Some(cost) = cost.as_deref_mut() {
+                *cost += 1;
+            }
+            if !node.bounding_box.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count > 0 {
+                for primitive in &self.primitives[node.offset..node.offset + node.count] {
+                    if let Some(cost) = cost.as_deref_mut() {
+                        *cost += primitive.hit_cost(ray, t_min, closest_so_far);
+                    }
+                    if let Some(hit) = primitive.hit(ray, t_min, closest_so_far) {
+                        closest_so_far = hit.distance;
+                        best_hit = Some(hit);
+                    }
+                }
             } else {
-                Ordering::Greater
+                // Push the far child first so the near child is visited first and
+                // shrinks closest_so_far before the far child is tested.
+                let left = node.offset;
+                let right = node.offset + 1;
+                let (near, far) = if ray.direction[node.axis] < 0.0 {
+                    (right, left)
+                } else {
+                    (left, right)
+                };
+                stack[stack_len] = far;
+                stack[stack_len + 1] = near;
+                stack_len += 2;
             }
-        } else {
-            panic!("");
         }
+        best_hit
     }
 }
 
 impl Hitable for BvhNode {
-    fn hit(&self, ray: &super::Ray, t_min: f32, t_max: f32) -> Option<crate::hitable::RayHit> {
-        if self.bounding_box.hit(ray, t_min, t_max) {
-            let left_hit = self.left.hit(ray, t_min, t_max);
-            let right_hit = self.right.hit(ray, t_min, t_max);
-            match (left_hit, right_hit) {
-                (Some(left), Some(right)) => {
-                    if left.distance < right.distance {
-                        Some(left)
-                    } else {
-                        Some(right)
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<RayHit> {
+        self.closest_hit(ray, t_min, t_max, None)
+    }
+
+    fn hit_cost(&self, ray: &Ray, t_min: f32, t_max: f32) -> usize {
+        let mut cost = 0;
+        self.closest_hit(ray, t_min, t_max, Some(&mut cost));
+        cost
+    }
+
+    // Every primitive along the ray contributes, so the order of traversal does not matter
+    fn transmittance(&self, ray: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let mut transmittance = 1.0;
+        let mut stack = [0usize; MAX_TRAVERSAL_DEPTH];
+        let mut stack_len = 1;
+        while stack_len > 0 {
+            stack_len -= 1;
+            let node = &self.nodes[stack[stack_len]];
+            if !node.bounding_box.hit(ray, t_min, t_max) {
+                continue;
+            }
+            if node.count > 0 {
+                for primitive in &self.primitives[node.offset..node.offset + node.count] {
+                    transmittance *= primitive.transmittance(ray, t_min, t_max);
+                    if transmittance <= 0.0 {
+                        return 0.0;
                     }
                 }
-                (None, Some(right)) => Some(right),
-                (Some(left), None) => Some(left),
-                (None, None) => None,
+            } else {
+                stack[stack_len] = node.offset;
+                stack[stack_len + 1] = node.offset + 1;
+                stack_len += 2;
             }
-        } else {
-            None
         }
+        transmittance
     }
 
     fn bounding_box(&self, _: f32, _: f32) -> Option<AABB> {
-        Some(self.bounding_box)
+        Some(self.nodes[0].bounding_box)
+    }
+}
+
+/// The shape of a built [`BvhNode`]. The SAH cost is the expected cost of tracing a ray
+/// that hits the root box, in units of primitive intersections.
+pub struct BvhStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub depth: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub primitive_count: usize,
+    pub sah_cost: f32,
+}
+
+impl BvhStats {
+    pub fn mean_leaf_size(&self) -> f32 {
+        self.primitive_count as f32 / self.leaf_count as f32
+    }
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} leaves, depth {}, leaf size {}/{:.2}/{} (min/mean/max), SAH cost {:.2}",
+            self.node_count,
+            self.leaf_count,
+            self.depth,
+            self.min_leaf_size,
+            self.mean_leaf_size(),
+            self.max_leaf_size,
+            self.sah_cost
+        )
+    }
+}
+
+fn bin_index(item: &BuildItem, centroid_box: &AABB, axis: usize) -> usize {
+    let extent = centroid_box.max[axis] - centroid_box.min[axis];
+    let offset = (item.centroid[axis] - centroid_box.min[axis]) / extent;
+    ((offset * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+}
+
+// Moves the items matching the predicate to the front, returning how many there were
+fn partition(items: &mut [BuildItem], predicate: impl Fn(&BuildItem) -> bool) -> usize {
+    let mut mid = 0;
+    for index in 0..items.len() {
+        if predicate(&items[index]) {
+            items.swap(index, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+fn merge(a: Option<AABB>, b: Option<AABB>) -> Option<AABB> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(AABB::surrounding_box(&a, &b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+fn area(bounding_box: Option<AABB>) -> f32 {
+    bounding_box.map_or(0.0, |bounding_box| bounding_box.surface_area())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{BvhNode, MAX_DEPTH};
+    use crate::{
+        hitable::Hitable,
+        materials::Diffuse,
+        shapes::Sphere,
+        structures::{Ray, Vec3},
+    };
+
+    #[test]
+    fn limits_the_depth_of_uneven_trees() {
+        // Spheres along each axis, each seventeen times further out than the last, so that
+        // the furthest always has a bin to itself and each split peels off only that one
+        let material = Diffuse::arc(Vec3::new(0.5, 0.5, 0.5));
+        let mut spheres: Vec<Arc<dyn Hitable>> = Vec::new();
+        for axis in 0..3 {
+            for power in -15..=15 {
+                let distance = 17f32.powi(power);
+                let mut centre = [0.0; 3];
+                centre[axis] = distance;
+                let centre = Vec3::new(centre[0], centre[1], centre[2]);
+                spheres.push(Sphere::arc(centre, 0.25 * distance, material.clone()));
+            }
+        }
+        let bvh = BvhNode::new(&spheres, 0.0, 0.0);
+        assert!(bvh.stats().depth <= MAX_DEPTH + 1);
+
+        // Passes the deepest nodes, around the origin. The smallest spheres are too small
+        // to hit from this far away, so the nearest is found by testing every one
+        let ray = Ray::new(Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let hit = bvh.hit(&ray, 0.0, f32::MAX).unwrap();
+        let nearest = spheres
+            .iter()
+            .filter_map(|sphere| sphere.hit(&ray, 0.0, f32::MAX))
+            .map(|hit| hit.distance)
+            .fold(f32::MAX, f32::min);
+        assert_eq!(hit.distance, nearest);
+        assert_eq!(bvh.transmittance(&ray, 0.0, f32::MAX), 0.0);
     }
 }
@@ -0,0 +1,215 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use super::Vec3;
+
+// Ranges with more items than this have their halves built on separate threads
+const PARALLEL_BUILD_THRESHOLD: usize = 16 * 1024;
+
+/// Balanced kd-tree of items at points in space, for finding the items nearest a point.
+/// The tree is implicit in the order of the items: each range of items has its median as
+/// the node, splitting the items before it from those after it along the node's axis.
+pub struct KdTree<T> {
+    items: Vec<(Vec3, T)>,
+    // The splitting axis of the node at each index
+    axes: Vec<u8>,
+}
+
+// A candidate neighbour, ordered by distance so the furthest is at the top of the heap
+struct Candidate {
+    distance_squared: f32,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared.total_cmp(&other.distance_squared)
+    }
+}
+
+impl<T: Send> KdTree<T> {
+    pub fn new(mut items: Vec<(Vec3, T)>) -> Self {
+        let mut axes = vec![0; items.len()];
+        build(&mut items, &mut axes);
+        Self { items, axes }
+    }
+}
+
+impl<T> KdTree<T> {
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Up to `count` of the items nearest to `point`, and no further than `max_distance`
+    /// from it, with their squared distances from it, nearest first.
+    pub fn nearest(&self, point: &Vec3, count: usize, max_distance: f32) -> Vec<(f32, &T)> {
+        let mut heap = BinaryHeap::with_capacity(count + 1);
+        if count > 0 {
+            self.search(
+                point,
+                count,
+                max_distance.powi(2),
+                0,
+                self.items.len(),
+                &mut heap,
+            );
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|candidate| (candidate.distance_squared, &self.items[candidate.index].1))
+            .collect()
+    }
+
+    fn search(
+        &self,
+        point: &Vec3,
+        count: usize,
+        max_distance_squared: f32,
+        start: usize,
+        end: usize,
+        heap: &mut BinaryHeap<Candidate>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let middle = start + (end - start) / 2;
+        let (position, _) = &self.items[middle];
+        let axis = self.axes[middle] as usize;
+        let offset = point[axis] - position[axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.search(point, count, max_distance_squared, near.0, near.1, heap);
+        let distance_squared = (*point - *position).length_squared();
+        if distance_squared <= max_distance_squared {
+            heap.push(Candidate {
+                distance_squared,
+                index: middle,
+            });
+            if heap.len() > count {
+                heap.pop();
+            }
+        }
+        // Items on the far side of the plane can only be closer than the furthest
+        // candidate so far if the plane itself is
+        let furthest = if heap.len() == count {
+            heap.peek()
+                .map_or(max_distance_squared, |candidate| candidate.distance_squared)
+        } else {
+            max_distance_squared
+        };
+        if offset.powi(2) <= furthest {
+            self.search(point, count, max_distance_squared, far.0, far.1, heap);
+        }
+    }
+}
+
+// Orders the items so that each range's median splits it along the axis in which its
+// items are most spread out
+fn build<T: Send>(items: &mut [(Vec3, T)], axes: &mut [u8]) {
+    if items.is_empty() {
+        return;
+    }
+    let mut min = items[0].0;
+    let mut max = items[0].0;
+    for (position, _) in items.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    let extent = max - min;
+    let axis = if extent.x() >= extent.y() && extent.x() >= extent.z() {
+        0
+    } else if extent.y() >= extent.z() {
+        1
+    } else {
+        2
+    };
+
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+    axes[middle] = axis as u8;
+    let (left_items, right_items) = items.split_at_mut(middle);
+    let (left_axes, right_axes) = axes.split_at_mut(middle);
+    let right_items = &mut right_items[1..];
+    let right_axes = &mut right_axes[1..];
+    if left_items.len() > PARALLEL_BUILD_THRESHOLD {
+        rayon::join(
+            || build(left_items, left_axes),
+            || build(right_items, right_axes),
+        );
+    } else {
+        build(left_items, left_axes);
+        build(right_items, right_axes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::KdTree;
+    use crate::{random, structures::Vec3};
+
+    #[test]
+    fn finds_the_same_neighbours_as_a_linear_search() {
+        random::reseed(1);
+        let mut rng = random::rng();
+        let points: Vec<Vec3> = (0..2_000)
+            .map(|_| Vec3::new(rng.gen(), rng.gen::<f32>() * 0.5, rng.gen::<f32>() * 0.1))
+            .collect();
+        let tree = KdTree::new(
+            points
+                .iter()
+                .copied()
+                .enumerate()
+                .map(|(i, p)| (p, i))
+                .collect(),
+        );
+        assert_eq!(tree.len(), points.len());
+
+        for _ in 0..100 {
+            let point = Vec3::new(rng.gen(), rng.gen(), rng.gen());
+            for (count, max_distance) in [(1, 1.0), (10, 0.1), (50, 0.05), (5_000, 0.2)] {
+                let mut expected: Vec<(f32, usize)> = points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| ((point - *p).length_squared(), i))
+                    .filter(|(distance_squared, _)| {
+                        *distance_squared <= max_distance * max_distance
+                    })
+                    .collect();
+                expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+                expected.truncate(count);
+
+                let found: Vec<(f32, usize)> = tree
+                    .nearest(&point, count, max_distance)
+                    .into_iter()
+                    .map(|(distance_squared, i)| (distance_squared, *i))
+                    .collect();
+                assert_eq!(found, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::ops;
+
+use super::{Vec3, AABB};
+
+/// A row major 4x4 matrix, applied to column vectors. Only affine transforms are
+/// supported, so the bottom row is always 0, 0, 0, 1.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Matrix4 {
+    rows: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(rows: [[f32; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    pub fn identity() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(factors: Vec3) -> Self {
+        Self::new([
+            [factors.x(), 0.0, 0.0, 0.0],
+            [0.0, factors.y(), 0.0, 0.0],
+            [0.0, 0.0, factors.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Rotation about the x (0), y (1) or z (2) axis, counterclockwise when looking back
+    /// down the axis.
+    pub fn rotation(axis: usize, degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut matrix = Self::identity();
+        matrix.rows[i][i] = cos;
+        matrix.rows[i][j] = -sin;
+        matrix.rows[j][i] = sin;
+        matrix.rows[j][j] = cos;
+        matrix
+    }
+
+    pub fn rows(&self) -> [[f32; 4]; 4] {
+        self.rows
+    }
+
+    pub fn is_affine(&self) -> bool {
+        self.rows[3] == [0.0, 0.0, 0.0, 1.0]
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Self::new(rows)
+    }
+
+    /// The inverse of an affine matrix, or None if it collapses space onto a plane, line or point.
+    pub fn inverse(&self) -> Option<Self> {
+        let m = &self.rows;
+        let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+            m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+        };
+        // The adjugate of the upper 3x3 block
+        let adjugate = [
+            [
+                cofactor(1, 2, 1, 2),
+                -cofactor(0, 2, 1, 2),
+                cofactor(0, 1, 1, 2),
+            ],
+            [
+                -cofactor(1, 2, 0, 2),
+                cofactor(0, 2, 0, 2),
+                -cofactor(0, 1, 0, 2),
+            ],
+            [
+                cofactor(1, 2, 0, 1),
+                -cofactor(0, 2, 0, 1),
+                cofactor(0, 1, 0, 1),
+            ],
+        ];
+        let determinant =
+            m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+        // The determinant is at most the product of the rows' lengths, reached when they are
+        // perpendicular, so comparing against that judges flatness whatever the scale
+        let row_lengths: f32 = (0..3)
+            .map(|i| (0..3).map(|j| m[i][j] * m[i][j]).sum::<f32>().sqrt())
+            .product();
+        if determinant.abs() <= f32::EPSILON * row_lengths || !determinant.is_finite() {
+            return None;
+        }
+
+        let mut rows = [[0.0, 0.0, 0.0, 1.0]; 4];
+        for (row, adjugate_row) in rows.iter_mut().zip(adjugate) {
+            for j in 0..3 {
+                row[j] = adjugate_row[j] / determinant;
+            }
+            // Undo the translation after the linear part has been inverted
+            row[3] = -(0..3).map(|j| row[j] * m[j][3]).sum::<f32>();
+        }
+
+        Some(Self::new(rows))
+    }
+
+    pub fn transform_point(&self, point: &Vec3) -> Vec3 {
+        self.transform_vector(point) + Vec3::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    pub fn transform_vector(&self, vector: &Vec3) -> Vec3 {
+        let row = |i: usize| {
+            self.rows[i][0] * vector.x()
+                + self.rows[i][1] * vector.y()
+                + self.rows[i][2] * vector.z()
+        };
+        Vec3::new(row(0), row(1), row(2))
+    }
+
+    /// Bounds of the transformed corners of the box.
+    pub fn transform_box(&self, bounding_box: &AABB) -> AABB {
+        let (min, max) = (bounding_box.min, bounding_box.max);
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x() } else { max.x() },
+                if i & 2 == 0 { min.y() } else { max.y() },
+                if i & 4 == 0 { min.z() } else { max.z() },
+            )
+        };
+        let first = self.transform_point(&corner(0));
+        (1..8).fold(AABB::new(first, first), |acc, i| {
+            let point = self.transform_point(&corner(i));
+            AABB::surrounding_box(&acc, &AABB::new(point, point))
+        })
+    }
+}
+
+impl_op_ex!(*|a: &Matrix4, b: &Matrix4| -> Matrix4 {
+    let mut rows = [[0.0; 4]; 4];
+    for (i, row) in rows.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a.rows[i][k] * b.rows[k][j]).sum();
+        }
+    }
+    Matrix4::new(rows)
+});
@@ -1,11 +1,17 @@
 mod aabb;
 mod bvh;
 mod hitable_list;
+mod kd_tree;
+mod matrix4;
 mod ray;
 mod vec3;
+mod voxel_grid;
 
 pub use aabb::*;
 pub use bvh::*;
 pub use hitable_list::*;
+pub use kd_tree::*;
+pub use matrix4::*;
 pub use ray::*;
 pub use vec3::*;
+pub use voxel_grid::*;
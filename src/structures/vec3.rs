@@ -1,6 +1,8 @@
 use std::{fmt::Display, ops};
 
-use rand::{thread_rng, Rng};
+use rand::Rng;
+
+use crate::random;
 
 #[derive(Copy, Clone)]
 pub struct Vec3 {
@@ -73,9 +75,17 @@ impl Vec3 {
         }
     }
 
+    pub fn get_point_on_unit_sphere() -> Vec3 {
+        let mut rng = random::rng();
+        let z = 2.0 * rng.gen::<f32>() - 1.0;
+        let phi = 2.0 * std::f32::consts::PI * rng.gen::<f32>();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vec3::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
     pub fn get_point_in_unit_sphere() -> Vec3 {
         let mut point = Vec3::new(10.0, 10.0, 10.0);
-        let mut rng = thread_rng();
+        let mut rng = random::rng();
         while point.length_squared() >= 1.0 {
             point = 2.0 * Vec3::new(rng.gen(), rng.gen(), rng.gen()) - Vec3::new(1.0, 1.0, 1.0);
         }
@@ -0,0 +1,144 @@
+use super::Vec3;
+
+/// A dense grid of density, and optionally temperature, values at the centres of its
+/// voxels. Voxels are stored with x varying fastest, then y, then z.
+pub struct VoxelGrid {
+    dimensions: [usize; 3],
+    density: Vec<f32>,
+    temperature: Option<Vec<f32>>,
+    max_density: f32,
+    max_temperature: f32,
+}
+
+impl VoxelGrid {
+    /// Panics if a dimension is zero or a channel does not have one value per voxel.
+    pub fn new(dimensions: [usize; 3], density: Vec<f32>, temperature: Option<Vec<f32>>) -> Self {
+        assert!(
+            dimensions.iter().all(|&dimension| dimension > 0),
+            "A voxel grid must have at least one voxel along each axis"
+        );
+        let voxel_count: usize = dimensions.iter().product();
+        assert_eq!(
+            density.len(),
+            voxel_count,
+            "A voxel grid must have one density per voxel"
+        );
+        if let Some(temperature) = &temperature {
+            assert_eq!(
+                temperature.len(),
+                voxel_count,
+                "A voxel grid must have one temperature per voxel"
+            );
+        }
+        let max = |values: &[f32]| values.iter().fold(0.0f32, |max, &value| max.max(value));
+        Self {
+            dimensions,
+            max_density: max(&density),
+            max_temperature: temperature.as_deref().map_or(0.0, max),
+            density,
+            temperature,
+        }
+    }
+
+    pub fn dimensions(&self) -> [usize; 3] {
+        self.dimensions
+    }
+
+    pub fn has_temperature(&self) -> bool {
+        self.temperature.is_some()
+    }
+
+    pub fn max_density(&self) -> f32 {
+        self.max_density
+    }
+
+    pub fn max_temperature(&self) -> f32 {
+        self.max_temperature
+    }
+
+    /// Trilinearly interpolated density at a point, where the grid spans 0 to 1 on each axis.
+    pub fn density(&self, point: &Vec3) -> f32 {
+        self.interpolate(&self.density, point)
+    }
+
+    /// As [`VoxelGrid::density`], or zero if the grid has no temperatures.
+    pub fn temperature(&self, point: &Vec3) -> f32 {
+        self.temperature
+            .as_ref()
+            .map_or(0.0, |temperature| self.interpolate(temperature, point))
+    }
+
+    fn interpolate(&self, values: &[f32], point: &Vec3) -> f32 {
+        // Values sit at voxel centres, so points within half a voxel of the edge are clamped
+        let mut base = [0usize; 3];
+        let mut next = [0usize; 3];
+        let mut fraction = [0.0f32; 3];
+        for axis in 0..3 {
+            let last = self.dimensions[axis] - 1;
+            let position =
+                (point[axis] * self.dimensions[axis] as f32 - 0.5).clamp(0.0, last as f32);
+            base[axis] = position as usize;
+            next[axis] = (base[axis] + 1).min(last);
+            fraction[axis] = position - base[axis] as f32;
+        }
+        let value = |x: usize, y: usize, z: usize| {
+            values[(z * self.dimensions[1] + y) * self.dimensions[0] + x]
+        };
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let [x0, y0, z0] = base;
+        let [x1, y1, z1] = next;
+        let [tx, ty, tz] = fraction;
+        lerp(
+            lerp(
+                lerp(value(x0, y0, z0), value(x1, y0, z0), tx),
+                lerp(value(x0, y1, z0), value(x1, y1, z0), tx),
+                ty,
+            ),
+            lerp(
+                lerp(value(x0, y0, z1), value(x1, y0, z1), tx),
+                lerp(value(x0, y1, z1), value(x1, y1, z1), tx),
+                ty,
+            ),
+            tz,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VoxelGrid;
+    use crate::structures::Vec3;
+
+    #[test]
+    fn interpolates_between_voxel_centres() {
+        // Each voxel's value is its x + 10y + 100z, which trilinear interpolation
+        // reproduces exactly between the centres
+        let dimensions = [3, 2, 2];
+        let values: Vec<f32> = (0..12)
+            .map(|index| (index % 3 + 10 * (index / 3 % 2) + 100 * (index / 6)) as f32)
+            .collect();
+        let grid = VoxelGrid::new(dimensions, values, None);
+        let centre =
+            |x: f32, y: f32, z: f32| Vec3::new((x + 0.5) / 3.0, (y + 0.5) / 2.0, (z + 0.5) / 2.0);
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..3 {
+                    let expected = (x + 10 * y + 100 * z) as f32;
+                    let density = grid.density(&centre(x as f32, y as f32, z as f32));
+                    assert!(
+                        (density - expected).abs() < 1e-4,
+                        "{} != {}",
+                        density,
+                        expected
+                    );
+                }
+            }
+        }
+        let between = grid.density(&centre(0.5, 0.25, 0.75));
+        assert!((between - 78.0).abs() < 1e-4, "{}", between);
+        // Points beyond the outermost centres take the nearest centre's value
+        assert!((grid.density(&Vec3::new(0.0, 0.0, 0.0)) - 0.0).abs() < 1e-4);
+        assert!((grid.density(&Vec3::new(1.0, 1.0, 1.0)) - 112.0).abs() < 1e-4);
+        assert_eq!(grid.temperature(&centre(1.0, 1.0, 1.0)), 0.0);
+    }
+}
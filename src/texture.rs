@@ -0,0 +1,5 @@
+use crate::structures::Vec3;
+
+pub trait Texture: Sync + Send {
+    fn value(&self, u: f32, v: f32, point: &Vec3) -> Vec3;
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use crate::{structures::Vec3, texture::Texture};
+
+/// Alternates between two textures in a 3D grid of cubes with sides of length `scale`.
+pub struct CheckerTexture {
+    scale: f32,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { scale, even, odd }
+    }
+
+    pub fn arc(scale: f32, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Arc<dyn Texture> {
+        Arc::new(Self::new(scale, even, odd))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f32, v: f32, point: &Vec3) -> Vec3 {
+        let cell = (point.x() / self.scale).floor()
+            + (point.y() / self.scale).floor()
+            + (point.z() / self.scale).floor();
+        if cell.rem_euclid(2.0) < 1.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
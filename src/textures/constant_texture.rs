@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::{structures::Vec3, texture::Texture};
+
+pub struct ConstantTexture {
+    colour: Vec3,
+}
+
+impl ConstantTexture {
+    pub fn new(colour: Vec3) -> Self {
+        Self { colour }
+    }
+
+    pub fn arc(colour: Vec3) -> Arc<dyn Texture> {
+        Arc::new(Self::new(colour))
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _u: f32, _v: f32, _point: &Vec3) -> Vec3 {
+        self.colour
+    }
+}
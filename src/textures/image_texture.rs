@@ -0,0 +1,76 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use image::{codecs::hdr::HdrDecoder, ImageError};
+
+use crate::{structures::Vec3, texture::Texture};
+
+/// An image wrapped over the UV coordinates of a surface, with v running from the bottom
+/// of the image to the top.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl ImageTexture {
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "An image texture must have one colour per pixel"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let (width, height, pixels) = load_linear_image(path.as_ref())?;
+        Ok(Self::new(width, height, pixels))
+    }
+
+    pub fn arc(path: impl AsRef<Path>) -> Result<Arc<dyn Texture>, ImageError> {
+        Ok(Arc::new(Self::load(path)?))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _point: &Vec3) -> Vec3 {
+        let x = (u.clamp(0.0, 1.0) * self.width as f32) as usize;
+        let y = ((1.0 - v.clamp(0.0, 1.0)) * self.height as f32) as usize;
+        self.pixels[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+}
+
+/// Loads a Radiance HDR image, or any other image format supported by `image`, which is
+/// assumed to be gamma 2 encoded like our own output. Pixels are returned row by row from
+/// the top left.
+pub(crate) fn load_linear_image(path: &Path) -> Result<(usize, usize, Vec<Vec3>), ImageError> {
+    let is_hdr = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+    if is_hdr {
+        let file = File::open(path).map_err(ImageError::IoError)?;
+        let decoder = HdrDecoder::new(BufReader::new(file))?;
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()?
+            .iter()
+            .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]))
+            .collect();
+        Ok((metadata.width as usize, metadata.height as usize, pixels))
+    } else {
+        let image = image::open(path)?.to_rgb8();
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b] = pixel.0.map(|channel| (channel as f32 / 255.0).powi(2));
+                Vec3::new(r, g, b)
+            })
+            .collect();
+        Ok((image.width() as usize, image.height() as usize, pixels))
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use crate::{structures::Vec3, texture::Texture};
+
+use super::Perlin;
+
+const TURBULENCE_DEPTH: u32 = 7;
+
+/// Bands along the z axis, with their phase distorted by turbulence to form veins.
+pub struct MarbleTexture {
+    noise: Perlin,
+    scale: f32,
+    distortion: f32,
+    colour: Vec3,
+}
+
+impl MarbleTexture {
+    pub fn new(seed: u64, scale: f32, distortion: f32, colour: Vec3) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            distortion,
+            colour,
+        }
+    }
+
+    pub fn arc(seed: u64, scale: f32, distortion: f32, colour: Vec3) -> Arc<dyn Texture> {
+        Arc::new(Self::new(seed, scale, distortion, colour))
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _u: f32, _v: f32, point: &Vec3) -> Vec3 {
+        let phase = self.scale * point.z()
+            + self.distortion * self.noise.turbulence(point, TURBULENCE_DEPTH);
+        self.colour * 0.5 * (1.0 + phase.sin())
+    }
+}
@@ -0,0 +1,17 @@
+mod checker_texture;
+mod constant_texture;
+mod image_texture;
+mod marble_texture;
+mod noise_texture;
+mod perlin;
+mod turbulence_texture;
+mod wood_texture;
+
+pub use checker_texture::*;
+pub use constant_texture::*;
+pub use image_texture::*;
+pub use marble_texture::*;
+pub use noise_texture::*;
+pub use perlin::*;
+pub use turbulence_texture::*;
+pub use wood_texture::*;
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::{structures::Vec3, texture::Texture};
+
+use super::Perlin;
+
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f32,
+    colour: Vec3,
+}
+
+impl NoiseTexture {
+    pub fn new(seed: u64, scale: f32, colour: Vec3) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            colour,
+        }
+    }
+
+    pub fn arc(seed: u64, scale: f32, colour: Vec3) -> Arc<dyn Texture> {
+        Arc::new(Self::new(seed, scale, colour))
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f32, _v: f32, point: &Vec3) -> Vec3 {
+        self.colour * 0.5 * (1.0 + self.noise.noise(&(self.scale * point)))
+    }
+}
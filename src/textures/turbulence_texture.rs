@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use crate::{structures::Vec3, texture::Texture};
+
+use super::Perlin;
+
+pub struct TurbulenceTexture {
+    noise: Perlin,
+    scale: f32,
+    depth: u32,
+    colour: Vec3,
+}
+
+impl TurbulenceTexture {
+    pub fn new(seed: u64, scale: f32, depth: u32, colour: Vec3) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            depth,
+            colour,
+        }
+    }
+
+    pub fn arc(seed: u64, scale: f32, depth: u32, colour: Vec3) -> Arc<dyn Texture> {
+        Arc::new(Self::new(seed, scale, depth, colour))
+    }
+}
+
+impl Texture for TurbulenceTexture {
+    fn value(&self, _u: f32, _v: f32, point: &Vec3) -> Vec3 {
+        self.colour
+            * self
+                .noise
+                .turbulence(&(self.scale * point), self.depth)
+                .min(1.0)
+    }
+}
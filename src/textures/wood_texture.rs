@@ -0,0 +1,49 @@
+use std::{f32::consts, sync::Arc};
+
+use crate::{structures::Vec3, texture::Texture};
+
+use super::Perlin;
+
+const TURBULENCE_DEPTH: u32 = 4;
+
+/// Growth rings around the y axis, `scale` rings per unit, wobbled by turbulence.
+pub struct WoodTexture {
+    noise: Perlin,
+    scale: f32,
+    distortion: f32,
+    light: Vec3,
+    dark: Vec3,
+}
+
+impl WoodTexture {
+    pub fn new(seed: u64, scale: f32, distortion: f32, light: Vec3, dark: Vec3) -> Self {
+        Self {
+            noise: Perlin::new(seed),
+            scale,
+            distortion,
+            light,
+            dark,
+        }
+    }
+
+    pub fn arc(
+        seed: u64,
+        scale: f32,
+        distortion: f32,
+        light: Vec3,
+        dark: Vec3,
+    ) -> Arc<dyn Texture> {
+        Arc::new(Self::new(seed, scale, distortion, light, dark))
+    }
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _u: f32, _v: f32, point: &Vec3) -> Vec3 {
+        let radius = (point.x().powi(2) + point.z().powi(2)).sqrt();
+        let rings =
+            self.scale * radius + self.distortion * self.noise.turbulence(point, TURBULENCE_DEPTH);
+        // Sharpen the rings so the dark late wood is thinner than the light early wood
+        let t = (0.5 * (1.0 + (2.0 * consts::PI * rings).sin())).powi(3);
+        (1.0 - t) * self.light + t * self.dark
+    }
+}